@@ -1,10 +1,19 @@
+use std::ops::ControlFlow;
+
 use rustc_data_structures::fx::FxHashSet;
-use rustc_middle::ty::{self, ToPolyTraitRef, TyCtxt};
+use rustc_data_structures::sync::par_map;
+use rustc_hir::def_id::DefId;
+use rustc_macros::extension;
+use rustc_middle::ty::{
+    self, ToPolyTraitRef, Ty, TyCtxt, TypeFoldable, TypeSuperVisitable, TypeVisitable, TypeVisitor,
+    Upcast,
+};
 use rustc_span::symbol::Ident;
 use rustc_span::Span;
 pub use rustc_type_ir::elaborate::*;
 
-use crate::traits::{self, Obligation, ObligationCauseCode, PredicateObligation};
+use crate::infer::InferCtxt;
+use crate::traits::{self, Obligation, ObligationCause, ObligationCauseCode, PredicateObligation};
 
 pub fn anonymize_predicate<'tcx>(
     tcx: TyCtxt<'tcx>,
@@ -14,14 +23,45 @@ pub fn anonymize_predicate<'tcx>(
     tcx.reuse_or_mk_predicate(pred, new)
 }
 
+#[derive(Clone)]
 pub struct PredicateSet<'tcx> {
     tcx: TyCtxt<'tcx>,
-    set: FxHashSet<ty::Predicate<'tcx>>,
+    /// The kinds of the normalized predicates.
+    set: FxHashSet<ty::Binder<'tcx, ty::PredicateKind<'tcx>>>,
+    /// Maps each predicate to the key it is deduplicated by.
+    normalize: fn(TyCtxt<'tcx>, ty::Predicate<'tcx>) -> ty::Predicate<'tcx>,
 }
 
 impl<'tcx> PredicateSet<'tcx> {
     pub fn new(tcx: TyCtxt<'tcx>) -> Self {
-        Self { tcx, set: Default::default() }
+        Self::with_dedup_mode(tcx, DedupMode::Anonymized)
+    }
+
+    pub fn with_dedup_mode(tcx: TyCtxt<'tcx>, dedup_mode: DedupMode) -> Self {
+        match dedup_mode {
+            DedupMode::Anonymized => Self::with_normalizer(tcx, anonymize_predicate),
+            DedupMode::Structural => Self::with_normalizer(tcx, |_, pred| pred),
+        }
+    }
+
+    /// Creates a set that considers two predicates to be equal if `normalize` maps them
+    /// to the same predicate, e.g. to also ignore free regions. `normalize` should at
+    /// least anonymize bound vars, like [`anonymize_predicate`] does for [`Self::new`].
+    pub fn with_normalizer(
+        tcx: TyCtxt<'tcx>,
+        normalize: fn(TyCtxt<'tcx>, ty::Predicate<'tcx>) -> ty::Predicate<'tcx>,
+    ) -> Self {
+        Self { tcx, set: Default::default(), normalize }
+    }
+
+    /// Creates a set containing `preds`, deduplicated modulo the names of bound vars.
+    pub fn from_predicates(
+        tcx: TyCtxt<'tcx>,
+        preds: impl IntoIterator<Item = ty::Predicate<'tcx>>,
+    ) -> Self {
+        let mut set = Self::new(tcx);
+        set.extend(preds);
+        set
     }
 
     /// Adds a predicate to the set.
@@ -41,8 +81,55 @@ pub fn insert(&mut self, pred: ty::Predicate<'tcx>) -> bool {
         //    for<'b> Foo<&'b i32>
         //
         // to be considered equivalent. So normalize all late-bound
-        // regions before we throw things into the underlying set.
-        self.set.insert(anonymize_predicate(self.tcx, pred))
+        // regions before we throw things into the underlying set,
+        // unless we've been asked to compare predicates structurally.
+        self.set.insert((self.normalize)(self.tcx, pred).kind())
+    }
+
+    /// Returns whether the set contains `pred`, modulo the normalization of the set, i.e.
+    /// the names of bound vars unless the set compares predicates structurally.
+    pub fn contains(&self, pred: ty::Predicate<'tcx>) -> bool {
+        self.set.contains(&(self.normalize)(self.tcx, pred).kind())
+    }
+
+    /// Removes all predicates from the set, returning them in arbitrary order. Unless the
+    /// set compares predicates structurally, these are the normalized predicates, i.e. not
+    /// necessarily the ones that were inserted.
+    #[allow(rustc::potential_query_instability)]
+    pub fn drain(&mut self) -> impl Iterator<Item = ty::Predicate<'tcx>> + '_ {
+        let tcx = self.tcx;
+        self.set.drain().map(move |kind| tcx.mk_predicate(kind))
+    }
+
+    /// Reserves capacity for at least `additional` more predicates.
+    pub fn reserve(&mut self, additional: usize) {
+        self.set.reserve(additional);
+    }
+
+    /// The number of predicates the set can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.set.capacity()
+    }
+
+    /// Consumes the set, returning its predicates. See [`PredicateSet::drain`]
+    /// for which predicates these are.
+    #[allow(rustc::potential_query_instability)]
+    pub fn into_predicates(self) -> FxHashSet<ty::Predicate<'tcx>> {
+        let tcx = self.tcx;
+        self.set.into_iter().map(|kind| tcx.mk_predicate(kind)).collect()
+    }
+}
+
+/// The keys of the elaborator are normalized like any other predicate, so that a set with
+/// a custom normalizer deduplicates the elaborated predicates in the same way.
+impl<'tcx> VisitedSet<ty::Binder<'tcx, ty::PredicateKind<'tcx>>> for PredicateSet<'tcx> {
+    fn insert(&mut self, item: ty::Binder<'tcx, ty::PredicateKind<'tcx>>) -> bool {
+        let pred = self.tcx.mk_predicate(item);
+        PredicateSet::insert(self, pred)
+    }
+
+    fn clear(&mut self) {
+        self.set.clear()
     }
 }
 
@@ -58,10 +145,117 @@ fn extend_one(&mut self, pred: ty::Predicate<'tcx>) {
     }
 
     fn extend_reserve(&mut self, additional: usize) {
-        Extend::<ty::Predicate<'tcx>>::extend_reserve(&mut self.set, additional);
+        self.set.reserve(additional);
     }
 }
 
+/// Elaborates `clauses` and interns the resulting (deduplicated) closure as a
+/// list of clauses, e.g. to use it as the bounds of a synthesized item.
+pub fn elaborated_clauses_interned<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    clauses: impl IntoIterator<Item = ty::Clause<'tcx>>,
+) -> ty::Clauses<'tcx> {
+    tcx.mk_clauses_from_iter(elaborate(tcx, clauses))
+}
+
+/// Like [`elaborate`], but reserves room for `capacity` predicates in the set of visited
+/// predicates up front, e.g. when the approximate size of the closure is known.
+pub fn elaborate_with_capacity<'tcx, O: Elaboratable<TyCtxt<'tcx>>>(
+    tcx: TyCtxt<'tcx>,
+    obligations: impl IntoIterator<Item = O>,
+    capacity: usize,
+) -> Elaborator<'static, TyCtxt<'tcx>, O> {
+    let visited = FxHashSet::with_capacity_and_hasher(capacity, Default::default());
+    elaborate_with_set(tcx, obligations, visited)
+}
+
+/// Elaborates `new_seeds`, only yielding the predicates that are not already in
+/// `baseline`, e.g. the closure of some other set of seeds. Predicates in `baseline`
+/// are not elaborated any further either, since their closure is assumed to be part
+/// of `baseline` as well. The elaborator keeps track of visited predicates in a copy
+/// of `baseline`.
+pub fn elaborate_delta<'tcx, O: Elaboratable<TyCtxt<'tcx>>>(
+    tcx: TyCtxt<'tcx>,
+    new_seeds: impl IntoIterator<Item = O>,
+    baseline: &PredicateSet<'tcx>,
+) -> Elaborator<'static, TyCtxt<'tcx>, O, Vec<PendingObligation<O>>, PredicateSet<'tcx>> {
+    elaborate_with_set(tcx, new_seeds, baseline.clone())
+}
+
+/// Like [`elaborate`], but first resolves the inference variables of `obligations`
+/// as far as `infcx` is able to. This way, e.g. `Vec<?0>: 'a` elaborates to `u32: 'a`
+/// if `?0` has been unified with `u32`, instead of dropping the unresolved variable.
+/// Variables that are only unified after this call are not taken into account.
+pub fn elaborate_in_infcx<'tcx, O: Elaboratable<TyCtxt<'tcx>> + TypeFoldable<TyCtxt<'tcx>>>(
+    infcx: &InferCtxt<'tcx>,
+    obligations: impl IntoIterator<Item = O>,
+) -> Elaborator<'static, TyCtxt<'tcx>, O> {
+    elaborate(
+        infcx.tcx,
+        obligations.into_iter().map(|obligation| infcx.resolve_vars_if_possible(obligation)),
+    )
+}
+
+/// Elaborates each of `seeds` separately, returning the elaborated closure of each seed
+/// in the same order. The seeds are elaborated in parallel if the compiler runs with
+/// multiple threads.
+pub fn elaborate_par<'tcx, O: Elaboratable<TyCtxt<'tcx>> + Send>(
+    tcx: TyCtxt<'tcx>,
+    seeds: Vec<O>,
+) -> Vec<Vec<O>> {
+    par_map(seeds, |seed| elaborate(tcx, [seed]).collect())
+}
+
+/// Computes the outlives bounds implied by `clauses` and the structure of the types in them,
+/// e.g. `'a: 'b` and `T: 'b` for `&'a T: 'b`. Outlives bounds under binders are skipped.
+pub fn elaborated_region_bound_pairs<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    clauses: impl IntoIterator<Item = ty::Clause<'tcx>>,
+) -> Vec<ty::OutlivesPredicate<'tcx, ty::GenericArg<'tcx>>> {
+    elaborate(tcx, clauses).into_region_bound_pairs()
+}
+
+/// Elaborates the where-clauses of the `#[automatically_derived]` impl `impl_def_id`,
+/// e.g. the `T: Clone` bounds that `#[derive(Clone)]` adds for each type parameter,
+/// to explain why a derived impl does not apply. Returns `None` if the impl is not derived.
+pub fn elaborate_derived_impl_bounds<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    impl_def_id: DefId,
+) -> Option<Elaborator<'static, TyCtxt<'tcx>, ty::Clause<'tcx>>> {
+    if !tcx.is_automatically_derived(impl_def_id) {
+        return None;
+    }
+
+    let predicates = tcx.predicates_of(impl_def_id).instantiate_identity(tcx).predicates;
+    Some(elaborate(tcx, predicates))
+}
+
+/// Computes the obligations that the trait impl `impl_def_id` must satisfy because of the
+/// supertraits of the implemented trait, e.g. `X: Super` for `impl Sub for X` with
+/// `trait Sub: Super`. Returns no obligations for inherent impls, and for negative and
+/// reservation impls, which do not implement the trait.
+pub fn impl_required_supertrait_obligations<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    impl_def_id: DefId,
+) -> Vec<PredicateObligation<'tcx>> {
+    let Some(trait_ref) = tcx.impl_trait_ref(impl_def_id) else {
+        return vec![];
+    };
+    if tcx.impl_polarity(impl_def_id) != ty::ImplPolarity::Positive {
+        return vec![];
+    }
+
+    let cause = ObligationCause::dummy_with_span(tcx.def_span(impl_def_id));
+    let obligation =
+        Obligation::new(tcx, cause, tcx.param_env(impl_def_id), trait_ref.instantiate_identity());
+    // Skip the obligation for the implemented trait itself.
+    let trait_predicate = obligation.predicate;
+    elaborate(tcx, [obligation])
+        .filter_only_self()
+        .filter(|obligation| obligation.predicate != trait_predicate)
+        .collect()
+}
+
 /// For [`Obligation`], a sub-obligation is combined with the current obligation's
 /// param-env and cause code.
 impl<'tcx> Elaboratable<TyCtxt<'tcx>> for PredicateObligation<'tcx> {
@@ -100,6 +294,290 @@ fn child_with_derived_cause(
             predicate: clause.as_predicate(),
         }
     }
+
+    fn child_with_elaborated_cause(
+        &self,
+        clause: ty::Clause<'tcx>,
+        parent_trait_pred: Option<ty::PolyTraitPredicate<'tcx>>,
+    ) -> Self {
+        // The outlives components of a `TypeOutlives` clause are not derived from a
+        // trait predicate themselves, so attribute them to the trait predicate that
+        // the clause is derived from, if any.
+        let parent_trait_pred = parent_trait_pred
+            .or_else(|| self.cause.code().parent().and_then(|(_, parent_pred)| parent_pred));
+        let Some(parent_trait_pred) = parent_trait_pred else {
+            return self.child(clause);
+        };
+        // These obligations don't come from a bound of the trait, so pointing at
+        // one as `ImplDerived` would be misleading.
+        let cause = self
+            .cause
+            .clone()
+            .derived_cause(parent_trait_pred, ObligationCauseCode::WellFormedDerived);
+        Obligation {
+            cause,
+            param_env: self.param_env,
+            recursion_depth: 0,
+            predicate: clause.as_predicate(),
+        }
+    }
+}
+
+/// An obligation whose elaborated obligations all get the given cause instead of
+/// a cause derived from the obligation they are elaborated from, e.g. so that all
+/// errors point at a single synthesized span.
+pub struct WithFixedCause<'tcx>(pub PredicateObligation<'tcx>, pub ObligationCause<'tcx>);
+
+impl<'tcx> Elaboratable<TyCtxt<'tcx>> for WithFixedCause<'tcx> {
+    fn predicate(&self) -> ty::Predicate<'tcx> {
+        self.0.predicate
+    }
+
+    fn child(&self, clause: ty::Clause<'tcx>) -> Self {
+        let obligation = Obligation {
+            cause: self.1.clone(),
+            param_env: self.0.param_env,
+            recursion_depth: 0,
+            predicate: clause.as_predicate(),
+        };
+        WithFixedCause(obligation, self.1.clone())
+    }
+
+    fn child_with_derived_cause(
+        &self,
+        clause: ty::Clause<'tcx>,
+        _span: Span,
+        _parent_trait_pred: ty::PolyTraitPredicate<'tcx>,
+        _index: usize,
+    ) -> Self {
+        self.child(clause)
+    }
+}
+
+/// An obligation whose elaborated obligations are all in the given param-env instead of
+/// the param-env of the obligation they are elaborated from. Unlike
+/// [`ElaborateObligationsExt::rebind_param_env`], this keeps the param-env of the seed
+/// obligation itself.
+pub struct InParamEnv<'tcx>(pub PredicateObligation<'tcx>, pub ty::ParamEnv<'tcx>);
+
+impl<'tcx> Elaboratable<TyCtxt<'tcx>> for InParamEnv<'tcx> {
+    fn predicate(&self) -> ty::Predicate<'tcx> {
+        self.0.predicate
+    }
+
+    fn child(&self, clause: ty::Clause<'tcx>) -> Self {
+        let obligation = Obligation { param_env: self.1, ..self.0.child(clause) };
+        InParamEnv(obligation, self.1)
+    }
+
+    fn child_with_derived_cause(
+        &self,
+        clause: ty::Clause<'tcx>,
+        span: Span,
+        parent_trait_pred: ty::PolyTraitPredicate<'tcx>,
+        index: usize,
+    ) -> Self {
+        let obligation = Obligation {
+            param_env: self.1,
+            ..self.0.child_with_derived_cause(clause, span, parent_trait_pred, index)
+        };
+        InParamEnv(obligation, self.1)
+    }
+
+    fn child_with_elaborated_cause(
+        &self,
+        clause: ty::Clause<'tcx>,
+        parent_trait_pred: Option<ty::PolyTraitPredicate<'tcx>>,
+    ) -> Self {
+        let obligation = Obligation {
+            param_env: self.1,
+            ..self.0.child_with_elaborated_cause(clause, parent_trait_pred)
+        };
+        InParamEnv(obligation, self.1)
+    }
+}
+
+#[extension(pub trait ElaborateObligationsExt<'a, 'tcx>)]
+impl<'a, 'tcx> Elaborator<'a, TyCtxt<'tcx>, PredicateObligation<'tcx>> {
+    /// Makes all obligations yielded from now on, including the obligations
+    /// elaborated from them, use `param_env` instead of the param-env of the
+    /// obligation they were elaborated from.
+    fn rebind_param_env(self, param_env: ty::ParamEnv<'tcx>) -> Self {
+        self.map_pending(|obligation| obligation.param_env = param_env)
+    }
+
+    /// Makes all obligations yielded from now on point at `span`, e.g. the place where
+    /// the user relies on the elaborated bounds. The derived causes of elaborated
+    /// obligations still record the span of the supertrait bound they come from, so
+    /// diagnostics point at `span` and mention the supertrait bound in a note.
+    fn with_usage_span(self, span: Span) -> Self {
+        self.map_pending(|obligation| obligation.cause.span = span)
+    }
+}
+
+/// Helpers to debug elaboration, which are only available with debug assertions.
+#[cfg(debug_assertions)]
+pub trait ElaborateDebugExt {
+    /// Drives the elaborator to completion, rendering the elaborated predicates as a graph
+    /// in the Graphviz DOT format, to help debug why some predicate is implied. Edges point
+    /// from a trait predicate to the predicates elaborated from it, and are labeled by the
+    /// path of the trait and the index of the elaborated predicate in its super-predicates.
+    fn render_graph(self) -> String;
+}
+
+#[cfg(debug_assertions)]
+impl<'tcx> ElaborateDebugExt for Elaborator<'_, TyCtxt<'tcx>, PredicateObligation<'tcx>> {
+    fn render_graph(self) -> String {
+        use std::fmt::Write;
+
+        let tcx = self.cx();
+        let mut dot = String::from("digraph elaboration {\n");
+        for obligation in self {
+            let predicate = dot_id(&obligation.predicate.to_string());
+            writeln!(dot, "    {predicate};").unwrap();
+            if let ObligationCauseCode::ImplDerived(cause) = obligation.cause.code() {
+                let parent = dot_id(&cause.derived.parent_trait_pred.to_string());
+                let mut label = tcx.def_path_str(cause.impl_or_alias_def_id);
+                if let Some(index) = cause.impl_def_predicate_index {
+                    write!(label, "#{index}").unwrap();
+                }
+                writeln!(dot, "    {parent} -> {predicate} [label={}];", dot_id(&label)).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Quotes `s` to be used as an ID in the Graphviz DOT format.
+#[cfg(debug_assertions)]
+fn dot_id(s: &str) -> String {
+    let mut id = String::with_capacity(s.len() + 2);
+    id.push('"');
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                id.push('\\');
+                id.push(c);
+            }
+            '\n' => id.push_str("\\n"),
+            _ => id.push(c),
+        }
+    }
+    id.push('"');
+    id
+}
+
+#[extension(pub trait ElaborateSortedExt<'a, 'tcx, O>)]
+impl<'a, 'tcx, O: Elaboratable<TyCtxt<'tcx>>> Elaborator<'a, TyCtxt<'tcx>, O> {
+    /// Collects the elaborated obligations, sorted by the stable hash of their anonymized
+    /// predicate. Unlike the order in which they are yielded, this order doesn't depend
+    /// on the order of the initial obligations, e.g. for snapshot tests.
+    fn into_sorted_vec(self) -> Vec<O> {
+        let tcx = self.cx();
+        let mut obligations: Vec<_> = self.collect();
+        obligations
+            .sort_by_cached_key(|obligation| tcx.anonymized_predicate_hash(obligation.predicate()));
+        obligations
+    }
+}
+
+#[extension(pub trait ElaborateWithHashExt<'a, 'tcx, O, Q, V>)]
+impl<'a, 'tcx, O, Q, V> Elaborator<'a, TyCtxt<'tcx>, O, Q, V>
+where
+    O: Elaboratable<TyCtxt<'tcx>>,
+    Q: WorkQueue<PendingObligation<O>>,
+    V: VisitedSet<ty::Binder<'tcx, ty::PredicateKind<'tcx>>>,
+{
+    /// Pairs each elaborated obligation with the
+    /// [`anonymized_predicate_hash`](TyCtxt::anonymized_predicate_hash) of its predicate,
+    /// for external tooling that wants to deduplicate or cache elaborated predicates.
+    fn with_hash(self) -> impl Iterator<Item = (O, u64)> {
+        let tcx = self.cx();
+        self.map(move |obligation| {
+            let hash = tcx.anonymized_predicate_hash(obligation.predicate()).as_u64();
+            (obligation, hash)
+        })
+    }
+}
+
+#[extension(pub trait ElaborateMentioningExt<'a, 'tcx, O>)]
+impl<'a, 'tcx, O: Elaboratable<TyCtxt<'tcx>>> Elaborator<'a, TyCtxt<'tcx>, O> {
+    /// Only yields the elaborated obligations whose predicate mentions `def_id`, e.g. as
+    /// the trait of a trait bound or somewhere in the types of the predicate. All other
+    /// obligations are still elaborated, as they may imply predicates that mention it.
+    fn mentioning(self, def_id: DefId) -> impl Iterator<Item = O> {
+        let tcx = self.cx();
+        self.filter(move |obligation| {
+            obligation.predicate().visit_with(&mut MentionsDefId { tcx, def_id }).is_break()
+        })
+    }
+}
+
+/// Finds the def-ids that are mentioned in a predicate, breaking once `def_id` is found.
+struct MentionsDefId<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    def_id: DefId,
+}
+
+impl<'tcx> MentionsDefId<'tcx> {
+    fn check(&self, def_id: DefId) -> ControlFlow<()> {
+        if def_id == self.def_id {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}
+
+impl<'tcx> TypeVisitor<TyCtxt<'tcx>> for MentionsDefId<'tcx> {
+    type Result = ControlFlow<()>;
+
+    fn visit_predicate(&mut self, pred: ty::Predicate<'tcx>) -> Self::Result {
+        match pred.kind().skip_binder() {
+            ty::PredicateKind::Clause(ty::ClauseKind::Trait(data)) => self.check(data.def_id())?,
+            ty::PredicateKind::Clause(ty::ClauseKind::Projection(data)) => {
+                self.check(data.def_id())?;
+                self.check(data.trait_def_id(self.tcx))?;
+            }
+            _ => {}
+        }
+        pred.super_visit_with(self)
+    }
+
+    fn visit_ty(&mut self, ty: Ty<'tcx>) -> Self::Result {
+        match *ty.kind() {
+            ty::Adt(adt_def, _) => self.check(adt_def.did())?,
+            ty::Foreign(def_id)
+            | ty::FnDef(def_id, _)
+            | ty::Closure(def_id, _)
+            | ty::CoroutineClosure(def_id, _)
+            | ty::Coroutine(def_id, _)
+            | ty::CoroutineWitness(def_id, _) => self.check(def_id)?,
+            ty::Alias(_, alias_ty) => self.check(alias_ty.def_id)?,
+            ty::Dynamic(preds, ..) => {
+                for pred in preds {
+                    match pred.skip_binder() {
+                        ty::ExistentialPredicate::Trait(data) => self.check(data.def_id)?,
+                        ty::ExistentialPredicate::Projection(data) => {
+                            self.check(data.def_id)?;
+                            self.check(self.tcx.parent(data.def_id))?;
+                        }
+                        ty::ExistentialPredicate::AutoTrait(def_id) => self.check(def_id)?,
+                    }
+                }
+            }
+            _ => {}
+        }
+        ty.super_visit_with(self)
+    }
+
+    fn visit_const(&mut self, ct: ty::Const<'tcx>) -> Self::Result {
+        if let ty::ConstKind::Unevaluated(uv) = ct.kind() {
+            self.check(uv.def)?;
+        }
+        ct.super_visit_with(self)
+    }
 }
 
 /// A specialized variant of `elaborate` that only elaborates trait references that may
@@ -136,3 +614,101 @@ pub fn transitive_bounds_that_define_assoc_item<'tcx>(
         None
     })
 }
+
+/// Returns the method groups from `#[rustc_must_implement_one_of]` attributes on
+/// `trait_def_id` and all of its supertraits, together with the trait they belong to.
+/// An impl of `trait_def_id` is only complete if the impls of each of these traits
+/// implement at least one method from the corresponding group.
+pub fn supertrait_must_implement_one_of<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    trait_def_id: DefId,
+) -> impl Iterator<Item = (DefId, &'tcx [Ident])> {
+    tcx.supertrait_def_ids(trait_def_id).filter_map(move |def_id| {
+        let group = tcx.trait_def(def_id).must_implement_one_of.as_deref()?;
+        Some((def_id, group))
+    })
+}
+
+/// Elaborates the supertraits of the principal trait of a trait object, e.g. `Eq` and
+/// `PartialOrd` for `dyn Ord`, including the principal itself. The principal is given the
+/// dummy self type of trait objects for this, which is then erased again. This is fine,
+/// as the supertraits of a trait always have the same self type as the trait itself.
+pub fn elaborate_existential<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    principal: ty::PolyExistentialTraitRef<'tcx>,
+) -> impl Iterator<Item = ty::PolyExistentialTraitRef<'tcx>> {
+    let trait_ref = principal.with_self_ty(tcx, tcx.types.trait_object_dummy_self);
+    supertraits(tcx, trait_ref).map(move |trait_ref| {
+        trait_ref.map_bound(|trait_ref| ty::ExistentialTraitRef::erase_self_ty(tcx, trait_ref))
+    })
+}
+
+/// The trait that is a supertrait of the traits of both `a` and `b` and nearest to them,
+/// e.g. to suggest a bound that unifies them. Only the traits of `a` and `b` are taken
+/// into account, not their generic args. See [`nearest_common_supertrait`] for how ties
+/// are broken.
+pub fn common_supertrait<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    a: ty::PolyTraitRef<'tcx>,
+    b: ty::PolyTraitRef<'tcx>,
+) -> Option<DefId> {
+    nearest_common_supertrait(tcx, a.def_id(), b.def_id())
+}
+
+/// Elaborates the supertraits of `trait_refs`, yielding at most one trait ref per trait,
+/// e.g. only one of `Foo<u32>` and `Foo<i32>` if both are supertraits. The trait ref that
+/// is kept is the first one in the order the elaborator yields them, which is depth-first
+/// and starts with the *last* of `trait_refs`.
+pub fn transitive_bounds_unique_traits<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    trait_refs: impl Iterator<Item = ty::PolyTraitRef<'tcx>>,
+) -> impl Iterator<Item = ty::PolyTraitRef<'tcx>> {
+    let mut seen_traits = FxHashSet::default();
+    elaborate(tcx, trait_refs.map(|trait_ref| -> ty::Clause<'tcx> { trait_ref.upcast(tcx) }))
+        .filter_only_self()
+        .filter_to_traits()
+        .filter(move |trait_ref| seen_traits.insert(trait_ref.def_id()))
+}
+
+/// Elaborates the supertraits of `trait_refs`, yielding each trait ref together with
+/// the span of the supertrait bound it was elaborated from. The seeds themselves are
+/// yielded with the span they were provided with. This is useful for diagnostics that
+/// need to point at the declaration that introduced an implied supertrait bound, which
+/// [`supertraits`] cannot do as its `Elaboratable` does not carry a span.
+pub fn transitive_bounds_with_spans<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    trait_refs: impl Iterator<Item = (ty::PolyTraitRef<'tcx>, Span)>,
+) -> impl Iterator<Item = (ty::PolyTraitRef<'tcx>, Span)> {
+    let mut elaborator = elaborate(
+        tcx,
+        trait_refs
+            .map(|(trait_ref, span)| -> (ty::Clause<'tcx>, Span) { (trait_ref.upcast(tcx), span) }),
+    )
+    .filter_only_self()
+    .with_span_tracking();
+
+    std::iter::from_fn(move || {
+        while let Some(elaborated) = elaborator.next_with_metadata() {
+            let (clause, seed_span) = elaborated.item;
+            // Outlives bounds on `Self` are not trait refs, and negative
+            // supertraits do not imply anything.
+            let Some(trait_pred) = clause.as_trait_clause() else {
+                continue;
+            };
+            if trait_pred.polarity() != ty::PredicatePolarity::Positive {
+                continue;
+            }
+
+            // The `(Clause, Span)` obligations keep the span of their seed, so take
+            // the span of the supertrait bound that was elaborated first instead.
+            let span = if elaborated.depth == 0 {
+                seed_span
+            } else {
+                elaborator.spans_of(clause.as_predicate())[0]
+            };
+            return Some((trait_pred.to_poly_trait_ref(), span));
+        }
+
+        None
+    })
+}
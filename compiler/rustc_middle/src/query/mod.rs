@@ -695,6 +695,24 @@
         }
     }
 
+    /// The def-ids of the transitive supertraits of the trait, including the trait itself.
+    /// This caches [`TyCtxt::supertrait_def_ids`], see [`TyCtxt::supertraits_cached`].
+    query supertrait_def_ids_closure(key: DefId) -> &'tcx FxIndexSet<DefId> {
+        arena_cache
+        desc { |tcx| "computing the transitive supertraits of `{}`", tcx.def_path_str(key) }
+    }
+
+    /// The transitive supertraits of the trait ref, including the trait ref itself, as
+    /// elaborated by [`rustc_type_ir::elaborate::supertraits`]. Unlike
+    /// `supertrait_def_ids_closure`, this keeps the generic args of each supertrait.
+    ///
+    /// The trait ref must not contain inference variables or placeholders, as those
+    /// are only meaningful within a single inference context. Use
+    /// [`rustc_type_ir::elaborate::supertraits`] directly for such trait refs.
+    query supertraits_of_trait_ref(key: ty::PolyTraitRef<'tcx>) -> &'tcx [ty::PolyTraitRef<'tcx>] {
+        desc { "computing the supertraits of `{}`", key }
+    }
+
     /// To avoid cycles within the predicates of a single item we compute
     /// per-type-parameter predicates for resolving `T::AssocTy`.
     query type_param_predicates(key: (LocalDefId, LocalDefId, rustc_span::symbol::Ident)) -> ty::GenericPredicates<'tcx> {
@@ -533,6 +533,10 @@ fn trait_is_fundamental(self, def_id: DefId) -> bool {
         self.trait_def(def_id).is_fundamental
     }
 
+    fn trait_is_marker(self, trait_def_id: DefId) -> bool {
+        self.trait_def(trait_def_id).is_marker
+    }
+
     fn trait_may_be_implemented_via_object(self, trait_def_id: DefId) -> bool {
         self.trait_def(trait_def_id).implement_via_object
     }
@@ -2504,6 +2508,22 @@ pub fn supertrait_def_ids(self, trait_def_id: DefId) -> impl Iterator<Item = Def
         rustc_type_ir::elaborate::supertrait_def_ids(self, trait_def_id)
     }
 
+    /// Like [`TyCtxt::supertrait_def_ids`], but the set of def-ids is cached for each trait,
+    /// so prefer this for commonly queried traits, e.g. those from the standard library.
+    pub fn supertraits_cached(self, trait_def_id: DefId) -> impl Iterator<Item = DefId> + 'tcx {
+        self.supertrait_def_ids_closure(trait_def_id).iter().copied()
+    }
+
+    /// The transitive supertraits of the trait with its own generic params as args, e.g.
+    /// `Self: Eq` and `Self: PartialEq<Self>` for `Eq`. This is the cached result of the
+    /// `supertraits_of_trait_ref` query, so all callers share the same slice. Unlike
+    /// other trait refs, the identity trait ref never contains inference variables or
+    /// placeholders, so it is always a valid key of that query.
+    pub fn identity_supertraits(self, trait_def_id: DefId) -> &'tcx [ty::PolyTraitRef<'tcx>] {
+        let trait_ref = ty::TraitRef::identity(self, trait_def_id);
+        self.supertraits_of_trait_ref(ty::Binder::dummy(trait_ref))
+    }
+
     /// Given a closure signature, returns an equivalent fn signature. Detuples
     /// and so forth -- so e.g., if we have a sig with `Fn<(u32, i32)>` then
     /// you would get a `fn(u32, i32)`.
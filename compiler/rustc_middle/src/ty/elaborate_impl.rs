@@ -1,8 +1,24 @@
+use rustc_data_structures::stable_hasher::{Hash64, HashStable, StableHasher};
+use rustc_span::source_map::Spanned;
 use rustc_span::Span;
 use rustc_type_ir::elaborate::Elaboratable;
 
 use crate::ty::{self, TyCtxt};
 
+impl<'tcx> TyCtxt<'tcx> {
+    /// Computes a stable hash of `pred` after anonymizing its bound vars, so that
+    /// e.g. `for<'a> Foo<&'a i32>` and `for<'b> Foo<&'b i32>` have the same hash.
+    /// This matches the way the elaborator deduplicates predicates by default.
+    pub fn anonymized_predicate_hash(self, pred: ty::Predicate<'tcx>) -> Hash64 {
+        let pred = self.reuse_or_mk_predicate(pred, self.anonymize_bound_vars(pred.kind()));
+        self.with_stable_hashing_context(|mut hcx| {
+            let mut hasher = StableHasher::new();
+            pred.hash_stable(&mut hcx, &mut hasher);
+            hasher.finish::<Hash64>()
+        })
+    }
+}
+
 impl<'tcx> Elaboratable<TyCtxt<'tcx>> for ty::Clause<'tcx> {
     fn predicate(&self) -> ty::Predicate<'tcx> {
         self.as_predicate()
@@ -82,3 +98,23 @@ fn child_with_derived_cause(
         (clause, self.1)
     }
 }
+
+impl<'tcx> Elaboratable<TyCtxt<'tcx>> for Spanned<ty::Clause<'tcx>> {
+    fn predicate(&self) -> ty::Predicate<'tcx> {
+        self.node.as_predicate()
+    }
+
+    fn child(&self, clause: ty::Clause<'tcx>) -> Self {
+        Spanned { node: clause, span: self.span }
+    }
+
+    fn child_with_derived_cause(
+        &self,
+        clause: ty::Clause<'tcx>,
+        _span: Span,
+        _parent_trait_pred: ty::PolyTraitPredicate<'tcx>,
+        _index: usize,
+    ) -> Self {
+        Spanned { node: clause, span: self.span }
+    }
+}
@@ -46,8 +46,8 @@
 pub use self::fulfill::{FulfillmentContext, OldSolverError, PendingPredicateObligation};
 pub use self::normalize::NormalizeExt;
 pub use self::object_safety::{
-    hir_ty_lowering_object_safety_violations, is_vtable_safe_method,
-    object_safety_violations_for_assoc_item, ObjectSafetyViolation,
+    elaborated_existential_projections, hir_ty_lowering_object_safety_violations,
+    is_vtable_safe_method, object_safety_violations_for_assoc_item, ObjectSafetyViolation,
 };
 pub use self::project::{normalize_inherent_projection, normalize_projection_ty};
 pub use self::select::{
@@ -62,9 +62,10 @@
 };
 pub use self::structural_normalize::StructurallyNormalizeExt;
 pub use self::util::{
-    elaborate, expand_trait_aliases, impl_item_is_final, supertraits,
-    transitive_bounds_that_define_assoc_item, upcast_choices, with_replaced_escaping_bound_vars,
-    BoundVarReplacer, PlaceholderReplacer, TraitAliasExpander, TraitAliasExpansionInfo,
+    drop_requirements, elaborate, elaborate_normalizing_aliases, expand_trait_aliases,
+    impl_item_is_final, supertraits, transitive_bounds_that_define_assoc_item, upcast_choices,
+    with_replaced_escaping_bound_vars, BoundVarReplacer, PlaceholderReplacer, TraitAliasExpander,
+    TraitAliasExpansionInfo,
 };
 use crate::error_reporting::InferCtxtErrorExt;
 use crate::infer::outlives::env::OutlivesEnvironment;
@@ -597,6 +598,16 @@ pub fn provide(providers: &mut Providers) {
         specialization_enabled_in: specialize::specialization_enabled_in,
         instantiate_and_check_impossible_predicates,
         is_impossible_associated_item,
+        supertrait_def_ids_closure: |tcx, trait_def_id| {
+            tcx.supertrait_def_ids(trait_def_id).collect()
+        },
+        supertraits_of_trait_ref: |tcx, trait_ref| {
+            assert!(
+                !trait_ref.has_infer() && !trait_ref.has_placeholders(),
+                "`supertraits_of_trait_ref` called with non-global trait ref: {trait_ref:?}"
+            );
+            tcx.arena.alloc_from_iter(supertraits(tcx, trait_ref))
+        },
         ..*providers
     };
 }
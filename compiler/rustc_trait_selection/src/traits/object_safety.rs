@@ -596,6 +596,22 @@ fn object_ty_for_trait<'tcx>(
     ));
     debug!(?trait_predicate);
 
+    let existential_predicates = tcx.mk_poly_existential_predicates_from_iter(
+        iter::once(trait_predicate).chain(elaborated_existential_projections(tcx, trait_ref)),
+    );
+    debug!(?existential_predicates);
+
+    Ty::new_dynamic(tcx, existential_predicates, lifetime, ty::Dyn)
+}
+
+/// Computes the projection bounds of the object type of `trait_ref` that are implied by
+/// the supertraits of the trait, e.g. `Target = Self::Target` for `Deref`, in the order
+/// in which they appear in the object type. These, together with `trait_ref` itself,
+/// make up the object type used to check whether methods can be dispatched on `dyn Trait`.
+pub fn elaborated_existential_projections<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    trait_ref: ty::TraitRef<'tcx>,
+) -> Vec<ty::PolyExistentialPredicate<'tcx>> {
     let pred: ty::Predicate<'tcx> = trait_ref.upcast(tcx);
     let mut elaborated_predicates: Vec<_> = elaborate(tcx, [pred])
         .filter_map(|pred| {
@@ -613,13 +629,7 @@ fn object_ty_for_trait<'tcx>(
     // predicate comes first.
     elaborated_predicates.sort_by(|a, b| a.skip_binder().stable_cmp(tcx, &b.skip_binder()));
     elaborated_predicates.dedup();
-
-    let existential_predicates = tcx.mk_poly_existential_predicates_from_iter(
-        iter::once(trait_predicate).chain(elaborated_predicates),
-    );
-    debug!(?existential_predicates);
-
-    Ty::new_dynamic(tcx, existential_predicates, lifetime, ty::Dyn)
+    elaborated_predicates
 }
 
 /// Checks the method's receiver (the `self` argument) can be dispatched on when `Self` is a
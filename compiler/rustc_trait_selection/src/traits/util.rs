@@ -1,6 +1,7 @@
 use std::collections::BTreeMap;
+use std::iter;
 
-use rustc_data_structures::fx::FxIndexMap;
+use rustc_data_structures::fx::{FxHashSet, FxIndexMap};
 use rustc_errors::Diag;
 use rustc_hir::def_id::DefId;
 use rustc_infer::infer::{InferCtxt, InferOk};
@@ -8,11 +9,12 @@
 use rustc_middle::bug;
 use rustc_middle::ty::{
     self, GenericArgsRef, ImplSubject, Ty, TyCtxt, TypeFoldable, TypeFolder, TypeSuperFoldable,
-    TypeVisitableExt, Upcast,
+    TypeSuperVisitable, TypeVisitable, TypeVisitableExt, TypeVisitor, Upcast,
 };
 use rustc_span::Span;
 use smallvec::{smallvec, SmallVec};
 
+use super::query::dropck_outlives::trivial_dropck_outlives;
 use super::{NormalizeExt, ObligationCause, PredicateObligation, SelectionContext};
 
 ///////////////////////////////////////////////////////////////////////////
@@ -208,6 +210,178 @@ pub fn upcast_choices<'tcx>(
     supertraits(tcx, source_trait_ref).filter(|r| r.def_id() == target_trait_def_id).collect()
 }
 
+/// Like [`elaborate`], but additionally decomposes the outlives bounds on aliases that
+/// normalize in `param_env`, e.g. `<T as Trait>::Assoc: 'a` into `'b: 'a` and `U: 'a`
+/// if `Assoc` normalizes to `&'b U`. The outlives bound on the alias is kept as well.
+/// Aliases are only decomposed if normalizing them doesn't require any obligations to
+/// hold, and doesn't leave inference variables behind. Normalizing happens in a probe,
+/// so `infcx` is left unchanged.
+///
+/// With the next trait solver, aliases are normalized lazily, so this does not decompose
+/// any aliases and is the same as [`elaborate`].
+pub fn elaborate_normalizing_aliases<'tcx>(
+    infcx: &InferCtxt<'tcx>,
+    param_env: ty::ParamEnv<'tcx>,
+    clauses: impl IntoIterator<Item = ty::Clause<'tcx>>,
+) -> Vec<ty::Clause<'tcx>> {
+    let tcx = infcx.tcx;
+    let mut clauses: Vec<_> = elaborate(tcx, clauses).collect();
+    if infcx.next_trait_solver() {
+        return clauses;
+    }
+    let mut seen =
+        PredicateSet::from_predicates(tcx, clauses.iter().map(|clause| clause.as_predicate()));
+
+    // The components of a normalized alias may be aliases themselves.
+    let mut i = 0;
+    while let Some(&clause) = clauses.get(i) {
+        i += 1;
+        let Some(ty::OutlivesPredicate(ty, r_min)) =
+            clause.as_type_outlives_clause().and_then(|outlives| outlives.no_bound_vars())
+        else {
+            continue;
+        };
+        if !matches!(ty.kind(), ty::Alias(..)) {
+            continue;
+        }
+
+        let normalized = infcx.probe(|_| {
+            let InferOk { value: normalized, obligations } =
+                infcx.at(&ObligationCause::dummy(), param_env).normalize(ty);
+            let normalized = infcx.resolve_vars_if_possible(normalized);
+            (obligations.is_empty() && normalized != ty && !normalized.has_infer())
+                .then_some(normalized)
+        });
+        let Some(normalized) = normalized else {
+            continue;
+        };
+
+        for component in outlives_components_as_clauses(tcx, normalized, r_min) {
+            let component: ty::Clause<'tcx> = component.upcast(tcx);
+            if seen.insert(component.as_predicate()) {
+                clauses.push(component);
+            }
+        }
+    }
+
+    clauses
+}
+
+/// Computes the elaborated bounds of the `Drop` impls that run when a value of type `ty`
+/// is dropped. This walks the components of `ty` that drop check considers to be dropped
+/// along with it, so e.g. for `Vec<Rc<T>>` this includes the bounds of the `Drop` impl of
+/// `Rc`, which `Vec` owns through a `PhantomData`. Components whose destructor is not
+/// known, like type params and trait objects, are skipped.
+///
+/// The bounds of a `Drop` impl that only mention its `#[may_dangle]` params are left out,
+/// as its destructor must not access values of those types.
+pub fn drop_requirements<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> Vec<ty::Clause<'tcx>> {
+    let mut seen = FxHashSet::default();
+    let mut stack = vec![ty];
+    let mut requirements = vec![];
+
+    while let Some(ty) = stack.pop() {
+        if trivial_dropck_outlives(tcx, ty) || !seen.insert(ty) {
+            continue;
+        }
+
+        match *ty.kind() {
+            ty::Pat(ty, _) | ty::Array(ty, _) | ty::Slice(ty) => stack.push(ty),
+            ty::Tuple(tys) => stack.extend(tys),
+            ty::Closure(_, args) => stack.extend(args.as_closure().upvar_tys()),
+            ty::CoroutineClosure(_, args) => stack.extend(args.as_coroutine_closure().upvar_tys()),
+            // Like drop check, assume that the values held by a coroutine across yield
+            // points are derived from its upvars and its resume argument.
+            ty::Coroutine(_, args) => {
+                let args = args.as_coroutine();
+                stack.extend(args.upvar_tys());
+                stack.push(args.resume_ty());
+            }
+            // `PhantomData` has no fields, but is considered to own a value of its type.
+            ty::Adt(adt_def, args) if adt_def.is_phantom_data() => stack.push(args.type_at(0)),
+            ty::Adt(adt_def, args) => {
+                if let Some(destructor) = adt_def.destructor(tcx) {
+                    requirements.extend(drop_impl_requirements(tcx, destructor.did, args));
+                }
+                stack.extend(adt_def.all_fields().map(|field| field.ty(tcx, args)));
+            }
+            _ => {}
+        }
+    }
+
+    elaborate(tcx, requirements).collect()
+}
+
+/// The bounds of the `Drop` impl of `destructor` for the ADT with `args`, leaving out the
+/// bounds that only mention `#[may_dangle]` params of the impl.
+fn drop_impl_requirements<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    destructor: DefId,
+    args: GenericArgsRef<'tcx>,
+) -> Vec<ty::Clause<'tcx>> {
+    let impl_def_id = tcx.parent(destructor);
+    let impl_generics = tcx.generics_of(impl_def_id);
+    // `Drop` impls have to be for the type with all of its generic params, but they may be
+    // in a different order than in the type itself. Other `Drop` impls are rejected by E0366.
+    let ty::Adt(_, impl_self_args) = *tcx.type_of(impl_def_id).instantiate_identity().kind() else {
+        return vec![];
+    };
+    let mut is_unconstrained = false;
+    let impl_args = ty::GenericArgs::for_item(tcx, impl_def_id, |param, _| {
+        let param = tcx.mk_param_from_def(param);
+        iter::zip(impl_self_args, args)
+            .find_map(|(self_arg, arg)| (self_arg == param).then_some(arg))
+            .unwrap_or_else(|| {
+                is_unconstrained = true;
+                param
+            })
+    });
+    if is_unconstrained {
+        return vec![];
+    }
+
+    tcx.predicates_of(impl_def_id)
+        .instantiate_identity(tcx)
+        .predicates
+        .into_iter()
+        .filter(|clause| {
+            let mut params = ParamIndices(vec![]);
+            clause.visit_with(&mut params);
+            params.0.is_empty()
+                || params
+                    .0
+                    .iter()
+                    .any(|&index| !impl_generics.param_at(index as usize, tcx).pure_wrt_drop)
+        })
+        .map(|clause| ty::EarlyBinder::bind(clause).instantiate(tcx, impl_args))
+        .collect()
+}
+
+/// Collects the indices of the generic params that a value mentions.
+struct ParamIndices(Vec<u32>);
+
+impl<'tcx> TypeVisitor<TyCtxt<'tcx>> for ParamIndices {
+    fn visit_ty(&mut self, ty: Ty<'tcx>) {
+        if let ty::Param(param) = *ty.kind() {
+            self.0.push(param.index);
+        }
+        ty.super_visit_with(self)
+    }
+
+    fn visit_region(&mut self, r: ty::Region<'tcx>) {
+        if let ty::ReEarlyParam(param) = r.kind() {
+            self.0.push(param.index);
+        }
+    }
+
+    fn visit_const(&mut self, ct: ty::Const<'tcx>) {
+        if let ty::ConstKind::Param(param) = ct.kind() {
+            self.0.push(param.index);
+        }
+        ct.super_visit_with(self)
+    }
+}
+
 pub fn closure_trait_ref_and_return_type<'tcx>(
     tcx: TyCtxt<'tcx>,
     fn_trait_def_id: DefId,
@@ -29,6 +29,22 @@ pub fn prepare_vtable_segments<'tcx, T>(
     prepare_vtable_segments_inner(tcx, trait_ref, segment_visitor).break_value()
 }
 
+/// Computes the traits that a trait object of `trait_ref` can be upcast to, i.e. the
+/// trait itself and all of its supertraits, in the order of their segments in the vtable.
+pub fn upcast_target_traits<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    trait_ref: ty::PolyTraitRef<'tcx>,
+) -> Vec<ty::PolyTraitRef<'tcx>> {
+    let mut targets = vec![];
+    prepare_vtable_segments(tcx, trait_ref, |segment| {
+        if let VtblSegment::TraitOwnEntries { trait_ref, .. } = segment {
+            targets.push(trait_ref);
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    targets
+}
+
 /// Helper for [`prepare_vtable_segments`] that returns `ControlFlow`,
 /// such that we can use `?` in the body.
 fn prepare_vtable_segments_inner<'tcx, T>(
@@ -1,10 +1,19 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::hash::Hash;
+use std::iter::FusedIterator;
 use std::marker::PhantomData;
+use std::rc::Rc;
 
-use smallvec::smallvec;
+use derive_where::derive_where;
+use smallvec::{smallvec, SmallVec};
+use tracing::{debug, instrument};
 
-use crate::data_structures::HashSet;
+use crate::data_structures::{HashMap, HashSet, IndexMap};
 use crate::inherent::*;
 use crate::outlives::{push_outlives_components, Component};
+use crate::solve::Goal;
+use crate::visit::TypeVisitableExt as _;
 use crate::{self as ty, Interner, Upcast as _};
 
 /// "Elaboration" is the process of identifying all the predicates that
@@ -13,16 +22,279 @@
 /// if we know that `T: Ord`, the elaborator would deduce that `T: PartialOrd`
 /// holds as well. Similarly, if we have `trait Foo: 'static`, and we know that
 /// `T: Foo`, then we know that `T: 'static`.
-pub struct Elaborator<I: Interner, O> {
+pub struct Elaborator<
+    'a,
+    I: Interner,
+    O,
+    Q = Vec<PendingObligation<O>>,
+    V = HashSet<ty::Binder<I, ty::PredicateKind<I>>>,
+> {
     cx: I,
-    stack: Vec<O>,
-    visited: HashSet<ty::Binder<I, ty::PredicateKind<I>>>,
+    /// The obligations that still have to be yielded and elaborated.
+    stack: Q,
+    visited: V,
     mode: Filter,
+    dedup_mode: DedupMode,
+    /// Whether to skip obligations we've already seen. Only disabled for debugging.
+    dedup: bool,
+    /// Obligations at this depth are yielded but not elaborated any further.
+    max_depth: Option<usize>,
+    /// Guards against unbounded elaboration, e.g. of very deep but acyclic
+    /// hierarchies. This is the recursion limit of `cx` unless overridden.
+    recursion_limit: Option<usize>,
+    /// Whether some clause was not elaborated because it reached one of the limits above.
+    truncated: bool,
+    /// The kinds of clauses to yield and elaborate, see [`Elaborator::only_kinds`].
+    kinds: ClauseKindSet,
+    /// Whether to emit a `WellFormed` obligation for the arguments of each trait clause.
+    well_formed: bool,
+    /// Whether to elaborate auto trait clauses on ADTs into clauses on their fields.
+    auto_traits: bool,
+    /// Whether to elaborate `ConstEvaluatable` clauses into their sub-expressions.
+    const_evaluatable: bool,
+    /// The opt-in bookkeeping and callbacks, which are only allocated once one of them
+    /// is enabled, so that elaborating without them stays cheap.
+    tracking: Option<Box<Tracking<'a, I, O>>>,
+    /// The index of the obligation being elaborated, in the order they are yielded.
+    current_parent: Option<usize>,
+    /// The number of obligations yielded so far.
+    yielded: usize,
+    /// The number of obligations checked against `visited`, and how many of them had
+    /// already been seen, see [`Elaborator::dedup_stats`].
+    dedup_attempts: usize,
+    dedup_hits: usize,
+    _marker: PhantomData<O>,
 }
 
-enum Filter {
+/// The opt-in state of an [`Elaborator`], see [`Elaborator::tracking`].
+#[derive_where(Default; I: Interner)]
+struct Tracking<'a, I: Interner, O> {
+    /// The number of predicates of each trait that may be elaborated, and how many of
+    /// them have been so far, see [`Elaborator::with_per_trait_budget`].
+    per_trait_budget: Option<(usize, HashMap<I::DefId, usize>)>,
+    /// If enabled, the supertrait bounds followed to reach each predicate,
+    /// see [`Elaborator::with_provenance`].
+    provenance: Option<Provenance<I>>,
+    /// While recording provenance, the provenance of the obligation being elaborated.
+    parent_provenance: Option<Vec<(I::DefId, usize)>>,
+    /// Additional elaboration rules, see [`Elaborator::with_expansion_rule`].
+    expansion_rule: Option<Box<ExpansionRule<'a, I, O>>>,
+    /// If enabled, the elaborated supertrait clauses that have more bound vars than
+    /// the clause they are elaborated from, see [`Elaborator::with_higher_ranked_tracking`].
+    higher_ranked: Option<Vec<I::Clause>>,
+    /// Rewrites each elaborated clause before it is enqueued, see [`Elaborator::map_clauses`].
+    map_clause: Option<Box<ClauseRewrite<'a, I>>>,
+    /// Called for each yielded obligation, see [`Elaborator::on_yield`].
+    on_yield: Option<Box<YieldCallback<'a, O>>>,
+    /// If enabled, the spans of all supertrait bounds that each predicate is
+    /// elaborated from, see [`Elaborator::with_span_tracking`].
+    spans: Option<SupertraitSpans<I>>,
+    /// If enabled, the parents each predicate was reached from,
+    /// see [`Elaborator::with_multi_path_tracking`].
+    parents: Option<IndexMap<I::Predicate, Vec<usize>>>,
+}
+
+/// A callback that pushes additional obligations elaborated from an obligation
+/// and its clause, see [`Elaborator::with_expansion_rule`].
+pub type ExpansionRule<'a, I, O> = dyn Fn(&O, <I as Interner>::Clause, &mut SmallVec<[O; 4]>) + 'a;
+
+/// A callback that rewrites each clause elaborated by the built-in elaboration rules,
+/// see [`Elaborator::map_clauses`].
+pub type ClauseRewrite<'a, I> =
+    dyn FnMut(I, <I as Interner>::Clause) -> <I as Interner>::Clause + 'a;
+
+/// A callback that is called with each yielded obligation, see [`Elaborator::on_yield`].
+pub type YieldCallback<'a, O> = dyn FnMut(&O) + 'a;
+
+/// The supertrait bounds followed to reach each predicate, see [`Elaborator::with_provenance`].
+type Provenance<I> =
+    HashMap<ty::Binder<I, ty::PredicateKind<I>>, Vec<(<I as Interner>::DefId, usize)>>;
+
+/// The spans of the supertrait bounds that each predicate is elaborated from,
+/// see [`Elaborator::with_span_tracking`].
+type SupertraitSpans<I> =
+    HashMap<ty::Binder<I, ty::PredicateKind<I>>, SmallVec<[<I as Interner>::Span; 2]>>;
+
+/// Used to tag the lifetime of an [`Elaborator`]'s callbacks in the `impl Iterator`s
+/// returned by its adapters, like `rustc_data_structures::captures::Captures`, which
+/// is not available without the `nightly` feature.
+pub trait Captures<'a> {}
+
+impl<'a, T: ?Sized> Captures<'a> for T {}
+
+/// An obligation that the elaborator has yet to yield and elaborate.
+pub struct PendingObligation<O> {
+    pub obligation: O,
+    /// How many elaboration steps this obligation is away from the initial obligations.
+    pub depth: usize,
+    /// The index, in the order they are yielded, of the obligation this obligation is
+    /// elaborated from, or `None` for the initial obligations.
+    pub parent: Option<usize>,
+}
+
+/// An elaborated obligation together with its position in the elaboration,
+/// see [`elaborate_with_metadata`].
+pub struct ElaboratedPredicate<O> {
+    pub item: O,
+    /// How many elaboration steps this obligation is away from the initial obligations.
+    pub depth: usize,
+    /// The index, in the order they are yielded, of the obligation this obligation is
+    /// elaborated from, or `None` for the initial obligations.
+    pub parent: Option<usize>,
+}
+
+/// The queue of obligations that the elaborator has yet to yield and elaborate.
+/// By default, this is a `Vec` used as a stack, but any queue can be used to
+/// change the order in which obligations are elaborated, e.g. a priority queue
+/// to elaborate some branches first. See [`Elaborator::with_queue`].
+pub trait WorkQueue<T> {
+    fn push(&mut self, item: T);
+
+    fn pop(&mut self) -> Option<T>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Calls `f` on all items in the queue, in no particular order.
+    fn for_each_mut(&mut self, f: impl FnMut(&mut T));
+}
+
+impl<T> WorkQueue<T> for Vec<T> {
+    fn push(&mut self, item: T) {
+        Vec::push(self, item)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        Vec::pop(self)
+    }
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn for_each_mut(&mut self, f: impl FnMut(&mut T)) {
+        self.iter_mut().for_each(f)
+    }
+}
+
+/// The set of predicates that the elaborator has already seen, keyed by [`DedupMode::key`].
+/// By default, this is a hash set, but e.g. a linear scan over a `SmallVec` can be
+/// faster for closures that are known to be tiny. See [`elaborate_with_set`].
+pub trait VisitedSet<T> {
+    /// Adds `item` to the set, returning whether it was newly inserted.
+    fn insert(&mut self, item: T) -> bool;
+
+    fn clear(&mut self);
+}
+
+impl<T: Hash + Eq> VisitedSet<T> for HashSet<T> {
+    fn insert(&mut self, item: T) -> bool {
+        HashSet::insert(self, item)
+    }
+
+    fn clear(&mut self) {
+        HashSet::clear(self)
+    }
+}
+
+impl<T: PartialEq, A: smallvec::Array<Item = T>> VisitedSet<T> for SmallVec<A> {
+    fn insert(&mut self, item: T) -> bool {
+        if self.contains(&item) {
+            false
+        } else {
+            self.push(item);
+            true
+        }
+    }
+
+    fn clear(&mut self) {
+        SmallVec::clear(self)
+    }
+}
+
+/// Which predicates the elaborator elaborates, see [`Elaborator::filter_mode`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    /// All implied predicates. This is the default.
     All,
+    /// Only the supertraits of trait predicates, see [`Elaborator::filter_only_self`].
     OnlySelf,
+    /// Only the supertraits of trait predicates that are marker traits, and only through
+    /// other marker traits, see [`Elaborator::filter_only_marker_supertraits`].
+    OnlyMarkerSupertraits,
+}
+
+/// How the elaborator decides whether it has already seen a predicate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DedupMode {
+    /// Predicates are compared after anonymizing their bound vars, so
+    /// `for<'a> Foo<&'a i32>` and `for<'b> Foo<&'b i32>` are considered equal.
+    /// This is the default.
+    Anonymized,
+    /// Predicates are compared as is, so only syntactically identical
+    /// predicates are deduplicated.
+    Structural,
+}
+
+impl DedupMode {
+    /// The key under which `predicate` is recorded in the set of visited predicates.
+    pub fn key<I: Interner>(
+        self,
+        cx: I,
+        predicate: I::Predicate,
+    ) -> ty::Binder<I, ty::PredicateKind<I>> {
+        match self {
+            DedupMode::Anonymized => cx.anonymize_bound_vars(predicate.kind()),
+            DedupMode::Structural => predicate.kind(),
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// A set of kinds of clauses, see [`Elaborator::only_kinds`].
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct ClauseKindSet: u8 {
+        const TRAIT = 1 << 0;
+        const REGION_OUTLIVES = 1 << 1;
+        const TYPE_OUTLIVES = 1 << 2;
+        const PROJECTION = 1 << 3;
+        const CONST_ARG_HAS_TYPE = 1 << 4;
+        const WELL_FORMED = 1 << 5;
+        const CONST_EVALUATABLE = 1 << 6;
+    }
+}
+
+impl ClauseKindSet {
+    /// The singleton set of the kind of `kind`.
+    pub fn of<I: Interner>(kind: ty::ClauseKind<I>) -> Self {
+        match kind {
+            ty::ClauseKind::Trait(..) => ClauseKindSet::TRAIT,
+            ty::ClauseKind::RegionOutlives(..) => ClauseKindSet::REGION_OUTLIVES,
+            ty::ClauseKind::TypeOutlives(..) => ClauseKindSet::TYPE_OUTLIVES,
+            ty::ClauseKind::Projection(..) => ClauseKindSet::PROJECTION,
+            ty::ClauseKind::ConstArgHasType(..) => ClauseKindSet::CONST_ARG_HAS_TYPE,
+            ty::ClauseKind::WellFormed(..) => ClauseKindSet::WELL_FORMED,
+            ty::ClauseKind::ConstEvaluatable(..) => ClauseKindSet::CONST_EVALUATABLE,
+        }
+    }
+}
+
+/// The number of predicates of each kind yielded by an elaboration,
+/// see [`Elaborator::count_by_clause_kind`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ClauseKindCounts {
+    pub trait_: usize,
+    pub projection: usize,
+    pub type_outlives: usize,
+    pub region_outlives: usize,
+    pub well_formed: usize,
+    pub const_evaluatable: usize,
+    pub const_arg_has_type: usize,
+    /// Predicates that are not clauses, which are yielded but never elaborated.
+    pub non_clause: usize,
 }
 
 /// Describes how to elaborate an obligation into a sub-obligation.
@@ -41,29 +313,372 @@ fn child_with_derived_cause(
         parent_trait_pred: ty::Binder<I, ty::TraitPredicate<I>>,
         index: usize,
     ) -> Self;
+
+    // Makes a new `Self` but with a different clause that is implied by `self` without
+    // coming from a supertrait bound, such as the `WellFormed` obligations for the
+    // arguments of the trait predicate `parent_trait_pred`, or the outlives components
+    // of a `TypeOutlives` clause, for which `parent_trait_pred` is `None`. By default,
+    // this is the same as `child`.
+    fn child_with_elaborated_cause(
+        &self,
+        clause: I::Clause,
+        _parent_trait_pred: Option<ty::Binder<I, ty::TraitPredicate<I>>>,
+    ) -> Self
+    where
+        Self: Sized,
+    {
+        self.child(clause)
+    }
+}
+
+/// An obligation tagged with whether it is a super-predicate of the trait predicate it is
+/// elaborated from, i.e. whether it constrains the same self type, like a supertrait bound,
+/// as opposed to e.g. a bound on an associated type. This distinguishes the predicates
+/// that would also be elaborated with [`Elaborator::filter_only_self`] from the other
+/// implied predicates. The initial obligations and predicates that are not elaborated from
+/// a trait predicate, e.g. outlives components, are not tagged.
+pub struct SuperTagged<O> {
+    pub obligation: O,
+    pub is_super: bool,
+}
+
+impl<O> SuperTagged<O> {
+    pub fn new(obligation: O) -> Self {
+        SuperTagged { obligation, is_super: false }
+    }
+}
+
+impl<I: Interner, O: Elaboratable<I>> Elaboratable<I> for SuperTagged<O> {
+    fn predicate(&self) -> I::Predicate {
+        self.obligation.predicate()
+    }
+
+    fn child(&self, clause: I::Clause) -> Self {
+        SuperTagged { obligation: self.obligation.child(clause), is_super: false }
+    }
+
+    fn child_with_derived_cause(
+        &self,
+        clause: I::Clause,
+        span: I::Span,
+        parent_trait_pred: ty::Binder<I, ty::TraitPredicate<I>>,
+        index: usize,
+    ) -> Self {
+        let self_ty = match clause.kind().skip_binder() {
+            ty::ClauseKind::Trait(data) => Some(data.self_ty()),
+            ty::ClauseKind::Projection(data) => Some(data.self_ty()),
+            ty::ClauseKind::TypeOutlives(ty::OutlivesPredicate(ty, _)) => Some(ty),
+            _ => None,
+        };
+        let is_super = self_ty == Some(parent_trait_pred.skip_binder().self_ty());
+        let obligation =
+            self.obligation.child_with_derived_cause(clause, span, parent_trait_pred, index);
+        SuperTagged { obligation, is_super }
+    }
+
+    fn child_with_elaborated_cause(
+        &self,
+        clause: I::Clause,
+        parent_trait_pred: Option<ty::Binder<I, ty::TraitPredicate<I>>>,
+    ) -> Self {
+        let obligation = self.obligation.child_with_elaborated_cause(clause, parent_trait_pred);
+        SuperTagged { obligation, is_super: false }
+    }
+}
+
+/// An obligation that records the span of every supertrait declaration it is elaborated
+/// through into a shared accumulator, so that diagnostics can collect them in the same
+/// pass as the elaboration itself.
+///
+/// All children share the accumulator of the obligation they are elaborated from. Since
+/// each trait predicate is only elaborated once, each declaration span is pushed once per
+/// distinct trait predicate it is reached from. The accumulator is borrowed mutably while
+/// a child is created, so it must not be borrowed by the caller while the elaborator is
+/// being advanced; read it once the elaborator is exhausted or dropped.
+pub struct SpanCollecting<I: Interner, O> {
+    pub obligation: O,
+    pub spans: Rc<RefCell<Vec<I::Span>>>,
+}
+
+impl<I: Interner, O> SpanCollecting<I, O> {
+    pub fn new(obligation: O, spans: Rc<RefCell<Vec<I::Span>>>) -> Self {
+        SpanCollecting { obligation, spans }
+    }
+}
+
+impl<I: Interner, O: Elaboratable<I>> Elaboratable<I> for SpanCollecting<I, O> {
+    fn predicate(&self) -> I::Predicate {
+        self.obligation.predicate()
+    }
+
+    fn child(&self, clause: I::Clause) -> Self {
+        SpanCollecting { obligation: self.obligation.child(clause), spans: self.spans.clone() }
+    }
+
+    fn child_with_derived_cause(
+        &self,
+        clause: I::Clause,
+        span: I::Span,
+        parent_trait_pred: ty::Binder<I, ty::TraitPredicate<I>>,
+        index: usize,
+    ) -> Self {
+        self.spans.borrow_mut().push(span);
+        let obligation =
+            self.obligation.child_with_derived_cause(clause, span, parent_trait_pred, index);
+        SpanCollecting { obligation, spans: self.spans.clone() }
+    }
+
+    fn child_with_elaborated_cause(
+        &self,
+        clause: I::Clause,
+        parent_trait_pred: Option<ty::Binder<I, ty::TraitPredicate<I>>>,
+    ) -> Self {
+        let obligation = self.obligation.child_with_elaborated_cause(clause, parent_trait_pred);
+        SpanCollecting { obligation, spans: self.spans.clone() }
+    }
 }
 
 pub fn elaborate<I: Interner, O: Elaboratable<I>>(
     cx: I,
     obligations: impl IntoIterator<Item = O>,
-) -> Elaborator<I, O> {
-    let mut elaborator =
-        Elaborator { cx, stack: Vec::new(), visited: HashSet::default(), mode: Filter::All };
-    elaborator.extend_deduped(obligations);
+) -> Elaborator<'static, I, O> {
+    elaborate_with_set(cx, obligations, HashSet::default())
+}
+
+/// Like [`elaborate`], but yields each obligation together with how many elaboration
+/// steps it is away from the initial obligations, and the index of the obligation it
+/// is elaborated from. Since the index is the position in the yielded sequence, this
+/// is enough to reconstruct the tree of the elaboration.
+pub fn elaborate_with_metadata<I: Interner, O: Elaboratable<I>>(
+    cx: I,
+    obligations: impl IntoIterator<Item = O>,
+) -> impl Iterator<Item = ElaboratedPredicate<O>> {
+    let mut elaborator = elaborate(cx, obligations);
+    std::iter::from_fn(move || elaborator.next_with_metadata())
+}
+
+/// Like [`elaborate`], but pairs each obligation with whether its predicate is expanded,
+/// see [`is_elaboratable_clause`].
+pub fn elaborate_flagged<I: Interner, O: Elaboratable<I>>(
+    cx: I,
+    obligations: impl IntoIterator<Item = O>,
+) -> impl Iterator<Item = (O, bool)> {
+    elaborate(cx, obligations).map(|obligation| {
+        let is_clause = is_elaboratable_clause::<I>(obligation.predicate());
+        (obligation, is_clause)
+    })
+}
+
+/// An elaborated predicate that references an error, see [`try_elaborate`].
+#[derive_where(Clone, Copy, Debug; I: Interner)]
+pub struct ElaborationError<I: Interner> {
+    /// The trait of the erroneous predicate, if it is a trait predicate.
+    pub def_id: Option<I::DefId>,
+    pub guar: I::ErrorGuaranteed,
+}
+
+/// Like [`elaborate`], but yields an error for each predicate that references an error.
+///
+/// Errors while computing the implied predicates of a trait, e.g. cycles, are reported
+/// by the query system, which then returns predicates containing error types or
+/// regions. Instead of passing these predicates on to a caller that may not expect
+/// them, this surfaces the proof that an error has already been emitted, so that e.g.
+/// external tools can bail out gracefully.
+pub fn try_elaborate<I: Interner, O: Elaboratable<I>>(
+    cx: I,
+    obligations: impl IntoIterator<Item = O>,
+) -> impl Iterator<Item = Result<O, ElaborationError<I>>> {
+    elaborate(cx, obligations).map(|obligation| {
+        let predicate = obligation.predicate();
+        match predicate.error_reported() {
+            Ok(()) => Ok(obligation),
+            Err(guar) => {
+                let def_id = predicate
+                    .as_clause()
+                    .and_then(|clause| clause.as_trait_clause())
+                    .map(|data| data.def_id());
+                Err(ElaborationError { def_id, guar })
+            }
+        }
+    })
+}
+
+/// Like [`elaborate`], but uses `visited` to keep track of the predicates that have
+/// already been seen. Predicates that are in `visited` from the start are neither
+/// yielded nor elaborated.
+pub fn elaborate_with_set<
+    I: Interner,
+    O: Elaboratable<I>,
+    V: VisitedSet<ty::Binder<I, ty::PredicateKind<I>>>,
+>(
+    cx: I,
+    obligations: impl IntoIterator<Item = O>,
+    visited: V,
+) -> Elaborator<'static, I, O, Vec<PendingObligation<O>>, V> {
+    let mut elaborator = Elaborator {
+        cx,
+        stack: Vec::new(),
+        visited,
+        mode: Filter::All,
+        dedup_mode: DedupMode::Anonymized,
+        dedup: true,
+        max_depth: None,
+        recursion_limit: Some(cx.recursion_limit()),
+        truncated: false,
+        kinds: ClauseKindSet::all(),
+        well_formed: false,
+        auto_traits: false,
+        const_evaluatable: false,
+        tracking: None,
+        current_parent: None,
+        yielded: 0,
+        dedup_attempts: 0,
+        dedup_hits: 0,
+        _marker: PhantomData,
+    };
+    elaborator.extend_deduped(0, obligations);
     elaborator
 }
 
-impl<I: Interner, O: Elaboratable<I>> Elaborator<I, O> {
-    fn extend_deduped(&mut self, obligations: impl IntoIterator<Item = O>) {
-        // Only keep those bounds that we haven't already seen.
-        // This is necessary to prevent infinite recursion in some
-        // cases. One common case is when people define
-        // `trait Sized: Sized { }` rather than `trait Sized { }`.
-        self.stack.extend(
-            obligations.into_iter().filter(|o| {
-                self.visited.insert(self.cx.anonymize_bound_vars(o.predicate().kind()))
-            }),
-        );
+/// Elaborates a single trait ref. This returns an unfiltered elaborator, so
+/// call [`Elaborator::filter_only_self`] if only the supertraits are needed.
+pub fn elaborate_trait_ref<I: Interner>(
+    cx: I,
+    trait_ref: ty::Binder<I, ty::TraitRef<I>>,
+) -> Elaborator<'static, I, I::Predicate> {
+    elaborate(cx, [trait_ref.upcast(cx)])
+}
+
+/// Elaborates each of `seeds` separately, taking turns between them so that a seed
+/// with a deep hierarchy does not starve the others, and stops after `total_fuel`
+/// obligations have been elaborated in total. Obligations reached from several
+/// seeds are only yielded once, but still consume fuel each time.
+pub fn elaborate_batch_fair<I: Interner, O: Elaboratable<I>>(
+    cx: I,
+    seeds: Vec<O>,
+    total_fuel: usize,
+) -> impl Iterator<Item = O> {
+    let mut elaborators: VecDeque<_> =
+        seeds.into_iter().map(|seed| elaborate(cx, [seed])).collect();
+    let mut visited: HashSet<_> = HashSet::default();
+    let mut fuel = total_fuel;
+
+    std::iter::from_fn(move || {
+        while fuel > 0 {
+            let mut elaborator = elaborators.pop_front()?;
+            let Some(obligation) = elaborator.next() else {
+                continue;
+            };
+            fuel -= 1;
+            elaborators.push_back(elaborator);
+
+            if visited.insert(DedupMode::Anonymized.key(cx, obligation.predicate())) {
+                return Some(obligation);
+            }
+        }
+        None
+    })
+}
+
+/// Elaborates `obligations` until we find one whose predicate is equal to `target`,
+/// modulo the names of bound vars. Since elaboration is lazy, this does not compute
+/// the rest of the closure once a match is found.
+pub fn elaborate_find<I: Interner, O: Elaboratable<I>>(
+    cx: I,
+    obligations: impl IntoIterator<Item = O>,
+    target: I::Predicate,
+) -> Option<O> {
+    let target = DedupMode::Anonymized.key(cx, target);
+    elaborate(cx, obligations).find(|o| DedupMode::Anonymized.key(cx, o.predicate()) == target)
+}
+
+/// Computes the graph of implications between the predicates in the elaborated
+/// closure of `predicates`, mapping each predicate to the predicates it directly
+/// implies. Unlike the elaborator itself, this also records edges to predicates
+/// that were already reached via another path, so for a diamond hierarchy the
+/// shared supertrait is the child of both sides.
+///
+/// Predicates are anonymized, so that the graph contains a single node for
+/// predicates which only differ in the names of their bound vars.
+pub fn implication_graph<I: Interner>(
+    cx: I,
+    predicates: impl IntoIterator<Item = I::Predicate>,
+) -> IndexMap<I::Predicate, Vec<I::Predicate>> {
+    let anonymize =
+        |pred: I::Predicate| -> I::Predicate { DedupMode::Anonymized.key(cx, pred).upcast(cx) };
+
+    let mut elaborator = elaborate(cx, predicates).with_multi_path_tracking();
+    // Parents are identified by the index of the obligation in the order they are yielded.
+    let nodes: Vec<_> = elaborator.by_ref().map(anonymize).collect();
+    let mut graph: IndexMap<I::Predicate, Vec<I::Predicate>> =
+        nodes.iter().map(|&node| (node, vec![])).collect();
+    let parents = elaborator.tracking.as_mut().and_then(|tracking| tracking.parents.take());
+    for (child, parents) in parents.unwrap_or_default() {
+        for parent in parents {
+            graph[&nodes[parent]].push(child);
+        }
+    }
+
+    graph
+}
+
+impl<
+        'a,
+        I: Interner,
+        O: Elaboratable<I>,
+        Q: WorkQueue<PendingObligation<O>>,
+        V: VisitedSet<ty::Binder<I, ty::PredicateKind<I>>>,
+    > Elaborator<'a, I, O, Q, V>
+{
+    fn extend_deduped(&mut self, depth: usize, obligations: impl IntoIterator<Item = O>) {
+        for obligation in obligations {
+            if !self.has_allowed_kind(&obligation) {
+                continue;
+            }
+
+            // Only keep those bounds that we haven't already seen.
+            // This is necessary to prevent infinite recursion in some
+            // cases. One common case is when people define
+            // `trait Sized: Sized { }` rather than `trait Sized { }`.
+            let key = self.dedup_mode.key(self.cx, obligation.predicate());
+
+            let parents = self.tracking.as_mut().and_then(|tracking| tracking.parents.as_mut());
+            if let (Some(parents), Some(parent)) = (parents, self.current_parent) {
+                let parents = parents.entry(key.upcast(self.cx)).or_default();
+                if !parents.contains(&parent) {
+                    parents.push(parent);
+                }
+            }
+
+            self.dedup_attempts += 1;
+            if !self.visited.insert(key) {
+                self.dedup_hits += 1;
+                if self.dedup {
+                    continue;
+                }
+            }
+
+            // Obligations elaborated other than via a supertrait bound, e.g. the
+            // components of an outlives bound, share the provenance of their parent.
+            if let Some(Tracking {
+                provenance: Some(provenance),
+                parent_provenance: Some(parent_provenance),
+                ..
+            }) = self.tracking.as_deref_mut()
+            {
+                provenance.entry(key).or_insert_with(|| parent_provenance.clone());
+            }
+
+            self.stack.push(PendingObligation { obligation, depth, parent: self.current_parent });
+        }
+    }
+
+    pub fn cx(&self) -> I {
+        self.cx
+    }
+
+    pub fn filter_mode(&self) -> Filter {
+        self.mode
     }
 
     /// Filter to only the supertraits of trait predicates, i.e. only the predicates
@@ -73,7 +688,606 @@ pub fn filter_only_self(mut self) -> Self {
         self
     }
 
-    fn elaborate(&mut self, elaboratable: &O) {
+    /// Filter to only the supertraits of trait predicates whose trait is a `#[marker]`
+    /// trait. As no other supertraits are ever enqueued, this only finds the marker
+    /// traits that are reachable through marker supertraits alone, e.g. not `Marker`
+    /// for `trait Trait: Super` with `trait Super: Marker`. In exchange, this is cheaper
+    /// than elaborating all supertraits and filtering them afterwards.
+    pub fn filter_only_marker_supertraits(mut self) -> Self {
+        self.mode = Filter::OnlyMarkerSupertraits;
+        self
+    }
+
+    /// Changes how predicates are deduplicated, see [`DedupMode`].
+    ///
+    /// This should be called before the elaborator is first advanced, as it only
+    /// re-keys the predicates that are still waiting to be elaborated.
+    pub fn dedup_mode(mut self, mode: DedupMode) -> Self {
+        let (cx, visited) = (self.cx, &mut self.visited);
+        visited.clear();
+        self.stack.for_each_mut(|pending| {
+            visited.insert(mode.key(cx, pending.obligation.predicate()));
+        });
+        self.dedup_mode = mode;
+        self
+    }
+
+    /// Do not elaborate obligations that are `depth` or more elaboration steps
+    /// away from the initial obligations. They are still yielded.
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Use `limit` instead of the recursion limit of the crate to guard
+    /// against unbounded elaboration, e.g. to stay responsive in tools.
+    /// Obligations reaching this depth are not elaborated any further.
+    pub fn with_recursion_limit(mut self, limit: usize) -> Self {
+        self.recursion_limit = Some(limit);
+        self
+    }
+
+    /// Do not limit the elaboration depth by the recursion limit, e.g. for callers
+    /// that really need the full closure of the initial obligations. Cycles are
+    /// still broken by deduplication.
+    pub fn without_recursion_limit(mut self) -> Self {
+        self.recursion_limit = None;
+        self.debug_assert_terminates();
+        self
+    }
+
+    /// Checks that elaboration is bounded by deduplication or by a depth limit.
+    fn debug_assert_terminates(&self) {
+        debug_assert!(
+            self.dedup || self.max_depth.is_some() || self.recursion_limit.is_some(),
+            "elaborating without deduplication requires a depth limit"
+        );
+    }
+
+    /// Elaborate using `queue` instead of the current queue. All pending obligations
+    /// are moved over to `queue`, in the order the current queue would yield them.
+    pub fn with_queue<Q2: WorkQueue<PendingObligation<O>>>(
+        mut self,
+        mut queue: Q2,
+    ) -> Elaborator<'a, I, O, Q2, V> {
+        while let Some(pending) = self.stack.pop() {
+            queue.push(pending);
+        }
+
+        Elaborator {
+            cx: self.cx,
+            stack: queue,
+            visited: self.visited,
+            mode: self.mode,
+            dedup_mode: self.dedup_mode,
+            dedup: self.dedup,
+            max_depth: self.max_depth,
+            recursion_limit: self.recursion_limit,
+            truncated: self.truncated,
+            kinds: self.kinds,
+            well_formed: self.well_formed,
+            auto_traits: self.auto_traits,
+            const_evaluatable: self.const_evaluatable,
+            tracking: self.tracking,
+            current_parent: self.current_parent,
+            yielded: self.yielded,
+            dedup_attempts: self.dedup_attempts,
+            dedup_hits: self.dedup_hits,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Only yield and elaborate clauses of the given kinds. Obligations that are not
+    /// clauses are not affected. Unlike filtering the elaborated obligations, this
+    /// also skips elaborating the clauses of the other kinds, so e.g. the supertraits
+    /// of a trait clause are not reached at all unless [`ClauseKindSet::TRAIT`] is
+    /// part of `kinds`.
+    pub fn only_kinds(mut self, kinds: ClauseKindSet) -> Self {
+        self.kinds = kinds;
+        let mut pending = Vec::with_capacity(self.stack.len());
+        while let Some(obligation) = self.stack.pop() {
+            pending.push(obligation);
+        }
+        for obligation in pending.into_iter().rev() {
+            if self.has_allowed_kind(&obligation.obligation) {
+                self.stack.push(obligation);
+            }
+        }
+        self
+    }
+
+    fn has_allowed_kind(&self, obligation: &O) -> bool {
+        // Avoid looking at the predicate in the common case of allowing all kinds.
+        self.kinds.is_all()
+            || obligation.predicate().as_clause().is_none_or(|clause| {
+                self.kinds.contains(ClauseKindSet::of(clause.kind().skip_binder()))
+            })
+    }
+
+    /// The opt-in state of the elaborator, which is allocated the first time it is needed.
+    fn tracking(&mut self) -> &mut Tracking<'a, I, O> {
+        self.tracking.get_or_insert_with(Default::default)
+    }
+
+    /// Calls `rule` for each clause that is elaborated, in addition to the built-in
+    /// elaboration rules, e.g. to experiment with new kinds of implied bounds. The
+    /// obligations that `rule` pushes are deduplicated like all other obligations.
+    /// `rule` may borrow from its environment, e.g. capture `cx` to build new clauses.
+    pub fn with_expansion_rule(
+        mut self,
+        rule: impl Fn(&O, I::Clause, &mut SmallVec<[O; 4]>) + 'a,
+    ) -> Self {
+        self.tracking().expansion_rule = Some(Box::new(rule));
+        self
+    }
+
+    /// Record the supertrait clauses whose instantiation introduces bound vars that
+    /// the clause they are elaborated from does not have, e.g. `T: for<'a> Super<'a>`
+    /// elaborated from `T: Trait`. This helps to debug binder mismatches.
+    /// See [`Elaborator::higher_ranked_clauses`].
+    pub fn with_higher_ranked_tracking(mut self) -> Self {
+        self.tracking().higher_ranked = Some(vec![]);
+        self
+    }
+
+    /// The higher-ranked clauses elaborated so far, if enabled via
+    /// [`Elaborator::with_higher_ranked_tracking`].
+    pub fn higher_ranked_clauses(&self) -> &[I::Clause] {
+        self.tracking
+            .as_ref()
+            .and_then(|tracking| tracking.higher_ranked.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Rewrites each clause elaborated by the built-in elaboration rules with `f` before
+    /// it is enqueued, e.g. to substitute a generic param throughout the implied bounds
+    /// of a trait. The rewritten clauses are deduplicated and elaborated in turn, so a
+    /// rewrite that keeps producing new clauses, e.g. by wrapping types, makes the
+    /// elaboration diverge. Consider combining this with [`Elaborator::with_max_depth`].
+    pub fn map_clauses(mut self, f: impl FnMut(I, I::Clause) -> I::Clause + 'a) -> Self {
+        self.tracking().map_clause = Some(Box::new(f));
+        self
+    }
+
+    /// Calls `f` with each obligation right before it is yielded. Unlike
+    /// [`Iterator::inspect`], this keeps the type of the elaborator, so that
+    /// e.g. [`Elaborator::filter_to_traits`] can still be called afterwards.
+    pub fn on_yield(mut self, f: impl FnMut(&O) + 'a) -> Self {
+        self.tracking().on_yield = Some(Box::new(f));
+        self
+    }
+
+    /// Record the spans of the supertrait bounds that each predicate is elaborated
+    /// from, even if the predicate was already reached before, e.g. via another
+    /// path in a diamond hierarchy. See [`Elaborator::spans_of`].
+    pub fn with_span_tracking(mut self) -> Self {
+        self.tracking().spans = Some(HashMap::default());
+        self
+    }
+
+    /// The spans of all supertrait bounds that `pred` has been elaborated from so far.
+    /// This is empty for the initial obligations, or if spans are not being tracked.
+    pub fn spans_of(&self, pred: I::Predicate) -> &[I::Span] {
+        self.tracking
+            .as_ref()
+            .and_then(|tracking| tracking.spans.as_ref())
+            .and_then(|spans| spans.get(&self.dedup_mode.key(self.cx, pred)))
+            .map_or(&[], |spans| spans.as_slice())
+    }
+
+    /// Record the obligations that each predicate is elaborated from, even if the predicate
+    /// was already reached before, see [`Elaborator::multi_path_predicates`].
+    pub fn with_multi_path_tracking(mut self) -> Self {
+        self.tracking().parents = Some(IndexMap::default());
+        self
+    }
+
+    /// The predicates that were elaborated from more than one obligation, e.g. the
+    /// shared supertrait of a diamond hierarchy, mapped to the indices of those
+    /// obligations in the order they were yielded. Requires
+    /// [`Elaborator::with_multi_path_tracking`], and is only complete once the
+    /// elaborator has been exhausted. Predicates are keyed by their [`DedupMode::key`].
+    pub fn multi_path_predicates(&self) -> IndexMap<I::Predicate, Vec<usize>> {
+        let Some(parents) = self.tracking.as_ref().and_then(|tracking| tracking.parents.as_ref())
+        else {
+            return IndexMap::default();
+        };
+
+        parents
+            .iter()
+            .filter(|(_, parents)| parents.len() > 1)
+            .map(|(pred, parents)| (*pred, parents.clone()))
+            .collect()
+    }
+
+    /// Treat `predicates` as already visited, so that they are neither yielded nor
+    /// elaborated when they are reached. This does not affect pending obligations.
+    pub fn mark_visited(mut self, predicates: impl IntoIterator<Item = I::Predicate>) -> Self {
+        for pred in predicates {
+            self.visited.insert(self.dedup_mode.key(self.cx, pred));
+        }
+        self
+    }
+
+    /// Yield obligations again each time they are reached, instead of only the
+    /// first time. This is useful to debug why some predicate is reached via
+    /// multiple paths.
+    ///
+    /// Without deduplication, elaboration only terminates for cyclic bounds such
+    /// as `trait Sized: Sized {}` because of the depth limit, so this cannot be
+    /// combined with [`Elaborator::without_recursion_limit`] unless a maximum
+    /// depth is set with [`Elaborator::with_max_depth`] first.
+    pub fn without_dedup(mut self) -> Self {
+        self.dedup = false;
+        self.debug_assert_terminates();
+        self
+    }
+
+    /// Also yield `WellFormed` obligations for the generic arguments of every trait
+    /// clause we elaborate, so that callers doing WF checking get the full set of
+    /// obligations from a single elaboration.
+    pub fn with_well_formed_obligations(mut self) -> Self {
+        self.well_formed = true;
+        self
+    }
+
+    /// Elaborate at most `budget` of the predicates implied by each trait, summed over
+    /// all clauses of that trait, so that a single trait with lots of implied predicates
+    /// cannot dominate the elaboration. Deduplicated predicates count as well.
+    pub fn with_per_trait_budget(mut self, budget: usize) -> Self {
+        self.tracking().per_trait_budget = Some((budget, HashMap::default()));
+        self
+    }
+
+    /// Elaborate auto trait clauses on ADTs into the same clause on each of their fields,
+    /// e.g. `S<T>: Send` into `T: Send` for `struct S<T>(T)`, following the way auto
+    /// trait impls are derived structurally. This is not done for ADTs with explicit
+    /// impls of the auto trait, whose impl may have other where-clauses. The clauses on
+    /// the fields are elaborated in turn, e.g. into the fields of nested ADTs.
+    pub fn expand_auto_traits(mut self) -> Self {
+        self.auto_traits = true;
+        self
+    }
+
+    /// Elaborate `ConstEvaluatable(N + M)` into `ConstEvaluatable(N)` and
+    /// `ConstEvaluatable(M)` whenever `N` and `M` are themselves non-trivial
+    /// const computations, so that all the const expressions that must be
+    /// evaluatable are discovered.
+    pub fn with_const_evaluatable_sub_exprs(mut self) -> Self {
+        self.const_evaluatable = true;
+        self
+    }
+
+    /// Record how each predicate is reached from the initial obligations, i.e. the
+    /// sequence of supertrait bounds that is followed, each step being the def-id of
+    /// the trait and the index of the bound in its implied predicates. The path can
+    /// be retrieved with [`Elaborator::provenance_of`].
+    ///
+    /// This should be called before the elaborator is first advanced.
+    pub fn with_provenance(mut self) -> Self {
+        let (cx, dedup_mode, mut provenance) = (self.cx, self.dedup_mode, HashMap::default());
+        self.stack.for_each_mut(|pending| {
+            provenance.insert(dedup_mode.key(cx, pending.obligation.predicate()), vec![]);
+        });
+        self.tracking().provenance = Some(provenance);
+        self
+    }
+
+    /// The supertrait bounds followed to reach `pred`, if provenance is being recorded
+    /// and `pred` has been reached so far. This is empty for the initial obligations.
+    pub fn provenance_of(&self, pred: I::Predicate) -> Option<&[(I::DefId, usize)]> {
+        let provenance = self.tracking.as_ref()?.provenance.as_ref()?;
+        provenance.get(&self.dedup_mode.key(self.cx, pred)).map(Vec::as_slice)
+    }
+
+    /// Applies `f` to all obligations that have not been yielded yet. Since elaborated
+    /// obligations are derived from the obligation they are elaborated from, this
+    /// affects all obligations the elaborator yields from now on.
+    pub fn map_pending(mut self, mut f: impl FnMut(&mut O)) -> Self {
+        self.stack.for_each_mut(|pending| f(&mut pending.obligation));
+        self
+    }
+
+    /// Applies `f` to each obligation as it is yielded. Unlike wrapping the elaborator
+    /// in [`Iterator::map`] this makes it explicit that `f` runs only after the children
+    /// of the obligation have been enqueued, so `f` does not affect what is elaborated
+    /// and must not be relied on for deduplication.
+    pub fn map_yield<T, F: FnMut(O) -> T>(self, f: F) -> impl Iterator<Item = T> + Captures<'a> {
+        self.map(f)
+    }
+
+    /// Turns each elaborated obligation into a goal of the new trait solver in `param_env`.
+    pub fn into_goals(
+        self,
+        param_env: I::ParamEnv,
+    ) -> impl Iterator<Item = Goal<I, I::Predicate>> + Captures<'a> {
+        let cx = self.cx;
+        self.map(move |elaboratable| Goal::new(cx, param_env, elaboratable.predicate()))
+    }
+
+    /// Drives the elaborator to completion, counting the yielded predicates by their kind.
+    pub fn count_by_clause_kind(self) -> ClauseKindCounts {
+        let mut counts = ClauseKindCounts::default();
+        for elaboratable in self {
+            let Some(clause) = elaboratable.predicate().as_clause() else {
+                counts.non_clause += 1;
+                continue;
+            };
+
+            match clause.kind().skip_binder() {
+                ty::ClauseKind::Trait(..) => counts.trait_ += 1,
+                ty::ClauseKind::Projection(..) => counts.projection += 1,
+                ty::ClauseKind::TypeOutlives(..) => counts.type_outlives += 1,
+                ty::ClauseKind::RegionOutlives(..) => counts.region_outlives += 1,
+                ty::ClauseKind::WellFormed(..) => counts.well_formed += 1,
+                ty::ClauseKind::ConstEvaluatable(..) => counts.const_evaluatable += 1,
+                ty::ClauseKind::ConstArgHasType(..) => counts.const_arg_has_type += 1,
+            }
+        }
+        counts
+    }
+
+    /// Whether `target` is the trait of one of the obligations or of one of their
+    /// supertraits. This only elaborates supertraits and stops as soon as `target`
+    /// is reached.
+    pub fn reaches_trait(self, target: I::DefId) -> bool {
+        self.filter_only_self().any(|elaboratable| {
+            elaboratable
+                .predicate()
+                .as_clause()
+                .and_then(|clause| clause.as_trait_clause())
+                .is_some_and(|data| data.def_id() == target)
+        })
+    }
+
+    /// Applies `f` to each elaborated obligation, stopping at the first error.
+    ///
+    /// Note that an obligation is elaborated when it is yielded, i.e. before `f` is
+    /// applied to it, so the children of the obligation that `f` fails on have
+    /// already been enqueued, and e.g. marked as visited, when the error is returned.
+    pub fn try_for_each<E, F: FnMut(O) -> Result<(), E>>(self, mut f: F) -> Result<(), E> {
+        for obligation in self {
+            f(obligation)?;
+        }
+        Ok(())
+    }
+
+    /// Drives the elaborator to completion, collecting the outlives clauses it yields as
+    /// outlives predicates on generic args, e.g. `'a: 'b` and `T: 'b` for `&'a T: 'b`, as
+    /// they are used for the implied bounds of an outlives environment. Higher-ranked
+    /// outlives clauses are skipped.
+    pub fn into_region_bound_pairs(self) -> Vec<ty::OutlivesPredicate<I, I::GenericArg>> {
+        self.filter_map(|elaboratable| {
+            match elaboratable.predicate().as_clause()?.kind().no_bound_vars()? {
+                ty::ClauseKind::RegionOutlives(ty::OutlivesPredicate(a, b)) => {
+                    Some(ty::OutlivesPredicate(a.into(), b))
+                }
+                ty::ClauseKind::TypeOutlives(ty::OutlivesPredicate(ty, r)) => {
+                    Some(ty::OutlivesPredicate(ty.into(), r))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+    }
+
+    /// Restarts the elaboration from `new_seeds`, keeping the configuration of the
+    /// elaborator as well as the allocations of its queue and of its visited set. This
+    /// avoids reallocating when elaborating many small sets of obligations in a loop.
+    ///
+    /// This takes the elaborator by value and returns the restarted one, so that an
+    /// exhausted elaborator keeps returning `None`, as [`FusedIterator`] requires.
+    pub fn reset(mut self, new_seeds: impl IntoIterator<Item = O>) -> Self {
+        while self.stack.pop().is_some() {}
+        self.visited.clear();
+        self.truncated = false;
+        self.current_parent = None;
+        self.yielded = 0;
+        self.dedup_attempts = 0;
+        self.dedup_hits = 0;
+        if let Some(tracking) = &mut self.tracking {
+            tracking.parent_provenance = None;
+            if let Some(spans) = &mut tracking.spans {
+                spans.clear();
+            }
+            if let Some(parents) = &mut tracking.parents {
+                parents.clear();
+            }
+            if let Some(higher_ranked) = &mut tracking.higher_ranked {
+                higher_ranked.clear();
+            }
+            if let Some((_, used)) = &mut tracking.per_trait_budget {
+                used.clear();
+            }
+        }
+        self.extend_deduped(0, new_seeds);
+        if let Some(Tracking { provenance: Some(provenance), .. }) = self.tracking.as_deref_mut() {
+            // Like in `with_provenance`, the new seeds are reached via no bounds at all.
+            provenance.clear();
+            let (cx, dedup_mode) = (self.cx, self.dedup_mode);
+            self.stack.for_each_mut(|pending| {
+                provenance.insert(dedup_mode.key(cx, pending.obligation.predicate()), vec![]);
+            });
+        }
+        self
+    }
+
+    /// Drives the elaborator to completion, returning the set of all predicates it
+    /// has seen, keyed by [`DedupMode::key`]. Use [`elaborate_with_set`] to choose
+    /// the type of that set, e.g. to reuse it as the baseline of another elaboration.
+    pub fn collect_predicate_set(mut self) -> V {
+        self.by_ref().for_each(drop);
+        self.visited
+    }
+
+    /// How many obligations were checked for whether they had already been seen so far,
+    /// and how many of them had. This helps to judge how effective deduplication is.
+    pub fn dedup_stats(&self) -> (usize, usize) {
+        (self.dedup_attempts, self.dedup_hits)
+    }
+
+    /// Whether some obligation that implies other predicates was yielded without being
+    /// elaborated because it reached the depth set by [`Elaborator::with_max_depth`] or
+    /// the recursion limit, or was only partially elaborated because of
+    /// [`Elaborator::with_per_trait_budget`]. If so, the elaborated closure may be
+    /// incomplete. Only meaningful once the elaborator has been exhausted, since this
+    /// only accounts for the obligations yielded so far.
+    pub fn was_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Whether we should elaborate an obligation at `depth`, or only yield it.
+    fn should_elaborate(&self, depth: usize) -> bool {
+        let below = |limit: Option<usize>| limit.is_none_or(|limit| depth < limit);
+        below(self.max_depth) && below(self.recursion_limit)
+    }
+
+    /// Yields all obligations that are currently pending and enqueues their
+    /// children, so that repeated calls walk the elaboration one level at a time,
+    /// breadth-first. Returns an empty vector once elaboration is done.
+    ///
+    /// This must not be mixed with calls to `next`, which may leave obligations
+    /// of several depths pending at once.
+    ///
+    /// With the default `Vec` queue, the obligations of each level are in the order
+    /// they were enqueued in. With a queue set by [`Elaborator::with_queue`], they are
+    /// in the reverse of the order the queue pops them in instead.
+    pub fn next_level(&mut self) -> Vec<O> {
+        let mut frontier = Vec::with_capacity(self.stack.len());
+        while let Some(pending) = self.stack.pop() {
+            frontier.push(pending);
+        }
+        // With the default stack, the pending obligations are popped in the reverse
+        // of the order they were pushed in, so restore that order.
+        frontier.into_iter().rev().map(|pending| self.yield_pending(pending)).collect()
+    }
+
+    /// Like `next`, but also returns the depth and the parent of the obligation,
+    /// see [`elaborate_with_metadata`].
+    pub fn next_with_metadata(&mut self) -> Option<ElaboratedPredicate<O>> {
+        let pending = self.stack.pop()?;
+        let (depth, parent) = (pending.depth, pending.parent);
+        Some(ElaboratedPredicate { item: self.yield_pending(pending), depth, parent })
+    }
+
+    /// Drives the elaborator to completion, returning the elaborated obligations in the
+    /// order they are yielded, together with the indices of the obligations directly
+    /// elaborated from each of them, in the same order. Since each predicate is only
+    /// yielded once, each obligation is only a child of the first obligation it was
+    /// reached from, so this is a forest rooted at the initial obligations. Obligations that
+    /// were already yielded before are not part of it.
+    pub fn build_adjacency(mut self) -> (Vec<O>, Vec<Vec<usize>>) {
+        // Parents are identified by their index among *all* yielded obligations.
+        let offset = self.yielded;
+        let mut nodes = vec![];
+        let mut children: Vec<Vec<usize>> = vec![];
+        while let Some(ElaboratedPredicate { item, depth: _, parent }) = self.next_with_metadata() {
+            if let Some(parent) = parent.and_then(|parent| parent.checked_sub(offset)) {
+                children[parent].push(nodes.len());
+            }
+            nodes.push(item);
+            children.push(vec![]);
+        }
+        (nodes, children)
+    }
+
+    /// Only yields the obligations elaborated from the initial obligations, not the
+    /// initial obligations themselves, which are still elaborated. Since predicates are
+    /// only yielded the first time they are reached, a predicate is not yielded if it
+    /// is also one of the initial obligations, unless deduplication is disabled.
+    pub fn skip_seeds(mut self) -> impl Iterator<Item = O> + Captures<'a> {
+        std::iter::from_fn(move || loop {
+            let elaborated = self.next_with_metadata()?;
+            if elaborated.depth > 0 {
+                return Some(elaborated.item);
+            }
+        })
+    }
+
+    /// Elaborates `pending` unless it is too deep, and returns its obligation.
+    fn yield_pending(&mut self, pending: PendingObligation<O>) -> O {
+        let PendingObligation { obligation, depth, parent: _ } = pending;
+        let index = self.yielded;
+        self.yielded += 1;
+        if self.should_elaborate(depth) {
+            if let Some(tracking) = &mut self.tracking {
+                if let Some(provenance) = &tracking.provenance {
+                    let key = self.dedup_mode.key(self.cx, obligation.predicate());
+                    tracking.parent_provenance =
+                        Some(provenance.get(&key).cloned().unwrap_or_default());
+                }
+            }
+            self.current_parent = Some(index);
+            self.elaborate(&obligation, depth);
+        } else if let Some(clause) = obligation.predicate().as_clause() {
+            if self.may_imply_predicates(clause) {
+                self.truncated = true;
+            }
+        }
+        if let Some(on_yield) =
+            self.tracking.as_mut().and_then(|tracking| tracking.on_yield.as_mut())
+        {
+            on_yield(&obligation);
+        }
+        obligation
+    }
+
+    /// Whether elaborating `clause` may yield any obligations, so that not elaborating it
+    /// because of a limit truncates the elaboration. Obligations that would be deduplicated
+    /// are not taken into account.
+    fn may_imply_predicates(&self, clause: I::Clause) -> bool {
+        let cx = self.cx;
+        if self.tracking.as_ref().is_some_and(|tracking| tracking.expansion_rule.is_some()) {
+            return true;
+        }
+
+        match clause.kind().skip_binder() {
+            ty::ClauseKind::Trait(data) => {
+                if data.polarity != ty::PredicatePolarity::Positive {
+                    return false;
+                }
+                if self.well_formed || (self.auto_traits && cx.trait_is_auto(data.def_id())) {
+                    return true;
+                }
+                match self.mode {
+                    Filter::All => cx
+                        .explicit_implied_predicates_of(data.def_id())
+                        .iter_identity()
+                        .next()
+                        .is_some(),
+                    Filter::OnlySelf => cx
+                        .explicit_super_predicates_of(data.def_id())
+                        .iter_identity()
+                        .next()
+                        .is_some(),
+                    Filter::OnlyMarkerSupertraits => cx
+                        .explicit_super_predicates_of(data.def_id())
+                        .iter_identity()
+                        .filter_map(|(clause, _)| clause.as_trait_clause())
+                        .any(|trait_pred| cx.trait_is_marker(trait_pred.def_id())),
+                }
+            }
+            ty::ClauseKind::TypeOutlives(ty::OutlivesPredicate(ty_max, r_min)) => {
+                !r_min.is_bound() && !outlives_components_as_clauses(cx, ty_max, r_min).is_empty()
+            }
+            ty::ClauseKind::ConstEvaluatable(ct) => {
+                self.const_evaluatable
+                    && !ct.has_bound_vars()
+                    && matches!(cx.expand_abstract_consts(ct).kind(), ty::ConstKind::Expr(_))
+            }
+            ty::ClauseKind::RegionOutlives(..)
+            | ty::ClauseKind::WellFormed(..)
+            | ty::ClauseKind::Projection(..)
+            | ty::ClauseKind::ConstArgHasType(..) => false,
+        }
+    }
+
+    #[instrument(
+        level = "debug",
+        skip(self, elaboratable),
+        fields(predicate = ?elaboratable.predicate())
+    )]
+    fn elaborate(&mut self, elaboratable: &O, depth: usize) {
         let cx = self.cx;
 
         // We only elaborate clauses.
@@ -81,6 +1295,37 @@ fn elaborate(&mut self, elaboratable: &O) {
             return;
         };
 
+        if let Some(rule) =
+            self.tracking.as_ref().and_then(|tracking| tracking.expansion_rule.as_ref())
+        {
+            let mut children = smallvec![];
+            rule(elaboratable, clause, &mut children);
+            self.extend_deduped(depth + 1, children);
+        }
+
+        // Take the rewrite out of `self`, so that it can be called while `self` is
+        // borrowed to enqueue the rewritten clauses.
+        let mut map_clause = self.tracking.as_mut().and_then(|tracking| tracking.map_clause.take());
+        let rewrite = |clause: I::Clause| match &mut map_clause {
+            Some(f) => f(cx, clause),
+            None => clause,
+        };
+        self.elaborate_clause(elaboratable, clause, depth, rewrite);
+        if let Some(tracking) = &mut self.tracking {
+            tracking.map_clause = map_clause;
+        }
+    }
+
+    /// Elaborates `clause`, the clause of `elaboratable`, with the built-in elaboration
+    /// rules, passing each elaborated clause through `rewrite` before it is enqueued.
+    fn elaborate_clause(
+        &mut self,
+        elaboratable: &O,
+        clause: I::Clause,
+        depth: usize,
+        mut rewrite: impl FnMut(I::Clause) -> I::Clause,
+    ) {
+        let cx = self.cx;
         let bound_clause = clause.kind();
         match bound_clause.skip_binder() {
             ty::ClauseKind::Trait(data) => {
@@ -88,32 +1333,170 @@ fn elaborate(&mut self, elaboratable: &O) {
                 if data.polarity != ty::PredicatePolarity::Positive {
                     return;
                 }
+                debug!(def_id = ?data.def_id(), "elaborating trait clause");
+
+                // If the trait ref holds, then it must also be well-formed.
+                if self.well_formed {
+                    self.extend_deduped(
+                        depth + 1,
+                        data.trait_ref.args.iter().map(|arg| {
+                            elaboratable.child_with_elaborated_cause(
+                                rewrite(
+                                    bound_clause.rebind(ty::ClauseKind::WellFormed(arg)).upcast(cx),
+                                ),
+                                Some(bound_clause.rebind(data)),
+                            )
+                        }),
+                    );
+                }
 
+                if self.auto_traits && cx.trait_is_auto(data.def_id()) {
+                    if let ty::Adt(adt, args) = data.self_ty().kind() {
+                        let mut has_explicit_impl = false;
+                        cx.for_each_relevant_impl(data.def_id(), data.self_ty(), |_| {
+                            has_explicit_impl = true
+                        });
+                        if !has_explicit_impl {
+                            self.extend_deduped(
+                                depth + 1,
+                                adt.all_field_tys(cx).iter_instantiated(cx, args).map(|field_ty| {
+                                    elaboratable.child(rewrite(
+                                        bound_clause
+                                            .rebind(ty::ClauseKind::Trait(
+                                                data.with_self_ty(cx, field_ty),
+                                            ))
+                                            .upcast(cx),
+                                    ))
+                                }),
+                            );
+                        }
+                    }
+                }
+
+                // `instantiate_supertrait` takes care of composing the binder of
+                // `data` with any binder on the supertrait clause, so that e.g.
+                // `for<'a> T: Trait<'a>` with `Trait<'a>: for<'b> Super<'a, 'b>`
+                // elaborates to `for<'a, 'b> T: Super<'a, 'b>`.
+                let dedup_mode = self.dedup_mode;
+                let (mut provenance, parent_path, mut spans, mut higher_ranked) =
+                    match &mut self.tracking {
+                        Some(tracking) => (
+                            tracking.provenance.take(),
+                            tracking.parent_provenance.take(),
+                            tracking.spans.take(),
+                            tracking.higher_ranked.take(),
+                        ),
+                        None => (None, None, None, None),
+                    };
                 let map_to_child_clause =
                     |(index, (clause, span)): (usize, (I::Clause, I::Span))| {
-                        elaboratable.child_with_derived_cause(
+                        let clause = rewrite(
                             clause.instantiate_supertrait(cx, bound_clause.rebind(data.trait_ref)),
+                        );
+                        if let Some(higher_ranked) = &mut higher_ranked {
+                            if clause.kind().bound_vars().len() > bound_clause.bound_vars().len() {
+                                higher_ranked.push(clause);
+                            }
+                        }
+                        let child = elaboratable.child_with_derived_cause(
+                            clause,
                             span,
                             bound_clause.rebind(data),
                             index,
-                        )
+                        );
+                        // Only the first path to a predicate is recorded, just like
+                        // only the first occurrence of a predicate is elaborated.
+                        if let (Some(provenance), Some(parent_path)) =
+                            (&mut provenance, &parent_path)
+                        {
+                            let key = dedup_mode.key(cx, child.predicate());
+                            provenance.entry(key).or_insert_with(|| {
+                                let mut path = parent_path.clone();
+                                path.push((data.def_id(), index));
+                                path
+                            });
+                        }
+                        if let Some(spans) = &mut spans {
+                            let key = dedup_mode.key(cx, child.predicate());
+                            spans.entry(key).or_default().push(span);
+                        }
+                        child
                     };
 
                 // Get predicates implied by the trait, or only super predicates if we only care about self predicates.
-                match self.mode {
-                    Filter::All => self.extend_deduped(
-                        cx.explicit_implied_predicates_of(data.def_id())
+                // If there is a budget for each trait, only take as many predicates as
+                // are left in the budget of this trait.
+                let per_trait_budget =
+                    self.tracking.as_ref().and_then(|tracking| tracking.per_trait_budget.as_ref());
+                let budget = per_trait_budget.map(|(budget, used)| {
+                    budget.saturating_sub(used.get(&data.def_id()).copied().unwrap_or(0))
+                });
+                let budget = budget.unwrap_or(usize::MAX);
+                let mut taken = 0;
+                let exceeded_budget = match self.mode {
+                    Filter::All => {
+                        let mut predicates = cx
+                            .explicit_implied_predicates_of(data.def_id())
                             .iter_identity()
-                            .enumerate()
-                            .map(map_to_child_clause),
-                    ),
-                    Filter::OnlySelf => self.extend_deduped(
-                        cx.explicit_super_predicates_of(data.def_id())
+                            .enumerate();
+                        self.extend_deduped(
+                            depth + 1,
+                            predicates
+                                .by_ref()
+                                .take(budget)
+                                .inspect(|_| taken += 1)
+                                .map(map_to_child_clause),
+                        );
+                        predicates.next().is_some()
+                    }
+                    Filter::OnlySelf => {
+                        let mut predicates = cx
+                            .explicit_super_predicates_of(data.def_id())
+                            .iter_identity()
+                            .enumerate();
+                        self.extend_deduped(
+                            depth + 1,
+                            predicates
+                                .by_ref()
+                                .take(budget)
+                                .inspect(|_| taken += 1)
+                                .map(map_to_child_clause),
+                        );
+                        predicates.next().is_some()
+                    }
+                    Filter::OnlyMarkerSupertraits => {
+                        let mut predicates = cx
+                            .explicit_super_predicates_of(data.def_id())
                             .iter_identity()
                             .enumerate()
-                            .map(map_to_child_clause),
-                    ),
+                            .filter(|(_, (clause, _))| {
+                                clause.as_trait_clause().is_some_and(|trait_pred| {
+                                    cx.trait_is_marker(trait_pred.def_id())
+                                })
+                            });
+                        self.extend_deduped(
+                            depth + 1,
+                            predicates
+                                .by_ref()
+                                .take(budget)
+                                .inspect(|_| taken += 1)
+                                .map(map_to_child_clause),
+                        );
+                        predicates.next().is_some()
+                    }
                 };
+                if exceeded_budget {
+                    self.truncated = true;
+                }
+                if let Some(tracking) = &mut self.tracking {
+                    if let Some((_, used)) = &mut tracking.per_trait_budget {
+                        *used.entry(data.def_id()).or_default() += taken;
+                    }
+                    tracking.provenance = provenance;
+                    tracking.parent_provenance = parent_path;
+                    tracking.spans = spans;
+                    tracking.higher_ranked = higher_ranked;
+                }
             }
             ty::ClauseKind::TypeOutlives(ty::OutlivesPredicate(ty_max, r_min)) => {
                 // We know that `T: 'a` for some type `T`. We can
@@ -134,13 +1517,14 @@ fn elaborate(&mut self, elaboratable: &O) {
                     return;
                 }
 
-                let mut components = smallvec![];
-                push_outlives_components(cx, ty_max, &mut components);
                 self.extend_deduped(
-                    components
-                        .into_iter()
-                        .filter_map(|component| elaborate_component_to_clause(cx, component, r_min))
-                        .map(|clause| elaboratable.child(bound_clause.rebind(clause).upcast(cx))),
+                    depth + 1,
+                    outlives_components_as_clauses(cx, ty_max, r_min).into_iter().map(|clause| {
+                        elaboratable.child_with_elaborated_cause(
+                            rewrite(bound_clause.rebind(clause).upcast(cx)),
+                            None,
+                        )
+                    }),
                 );
             }
             ty::ClauseKind::RegionOutlives(..) => {
@@ -148,14 +1532,47 @@ fn elaborate(&mut self, elaboratable: &O) {
             }
             ty::ClauseKind::WellFormed(..) => {
                 // Currently, we do not elaborate WF predicates,
-                // although we easily could.
+                // although we easily could. The WF obligations emitted
+                // by `with_well_formed_obligations` end up here too.
             }
             ty::ClauseKind::Projection(..) => {
                 // Nothing to elaborate in a projection predicate.
             }
-            ty::ClauseKind::ConstEvaluatable(..) => {
-                // Currently, we do not elaborate const-evaluatable
+            ty::ClauseKind::ConstEvaluatable(ct) => {
+                // By default, we do not elaborate const-evaluatable
                 // predicates.
+                if !self.const_evaluatable {
+                    return;
+                }
+
+                // We don't want to deal with bound vars right now.
+                if ct.has_bound_vars() {
+                    return;
+                }
+
+                // If `N + M` must be evaluatable, then so must `N` and `M`.
+                // We only care about those that are computations themselves.
+                if let ty::ConstKind::Expr(expr) = cx.expand_abstract_consts(ct).kind() {
+                    self.extend_deduped(
+                        depth + 1,
+                        expr.args()
+                            .iter()
+                            .filter_map(|arg| arg.as_const())
+                            .filter(|ct| {
+                                matches!(
+                                    ct.kind(),
+                                    ty::ConstKind::Unevaluated(_) | ty::ConstKind::Expr(_)
+                                )
+                            })
+                            .map(|ct| {
+                                elaboratable.child(rewrite(
+                                    bound_clause
+                                        .rebind(ty::ClauseKind::ConstEvaluatable(ct))
+                                        .upcast(cx),
+                                ))
+                            }),
+                    );
+                }
             }
             ty::ClauseKind::ConstArgHasType(..) => {
                 // Nothing to elaborate
@@ -164,6 +1581,43 @@ fn elaborate(&mut self, elaboratable: &O) {
     }
 }
 
+/// Whether [`elaborate`] expands `pred`, i.e. whether it is a positive trait clause or a
+/// type outlives clause. Other predicates, e.g. `Subtype` predicates or projection
+/// clauses, are only yielded. Whether expanding `pred` implies anything else depends
+/// on the trait or type it is about.
+pub fn is_elaboratable_clause<I: Interner>(pred: I::Predicate) -> bool {
+    let Some(clause) = pred.as_clause() else {
+        return false;
+    };
+    match clause.kind().skip_binder() {
+        ty::ClauseKind::Trait(data) => data.polarity == ty::PredicatePolarity::Positive,
+        ty::ClauseKind::TypeOutlives(..) => true,
+        // Only expanded with `Elaborator::with_const_evaluatable_sub_exprs`.
+        ty::ClauseKind::ConstEvaluatable(..) => false,
+        ty::ClauseKind::RegionOutlives(..)
+        | ty::ClauseKind::WellFormed(..)
+        | ty::ClauseKind::Projection(..)
+        | ty::ClauseKind::ConstArgHasType(..) => false,
+    }
+}
+
+/// Decomposes `ty: r_min` into the outlives clauses it implies for the components
+/// of `ty`, e.g. `&'a U: 'b` into `'a: 'b` and `U: 'b`. This is what elaborating a
+/// `TypeOutlives` clause yields. Components which are bound regions, unresolved
+/// inference variables or aliases with escaping bound vars are skipped.
+pub fn outlives_components_as_clauses<I: Interner>(
+    cx: I,
+    ty: I::Ty,
+    r_min: I::Region,
+) -> SmallVec<[ty::ClauseKind<I>; 4]> {
+    let mut components = smallvec![];
+    push_outlives_components(cx, ty, &mut components);
+    components
+        .into_iter()
+        .filter_map(|component| elaborate_component_to_clause(cx, component, r_min))
+        .collect()
+}
+
 fn elaborate_component_to_clause<I: Interner>(
     cx: I,
     component: Component<I>,
@@ -207,32 +1661,52 @@ fn elaborate_component_to_clause<I: Interner>(
     }
 }
 
-impl<I: Interner, O: Elaboratable<I>> Iterator for Elaborator<I, O> {
+impl<
+        'a,
+        I: Interner,
+        O: Elaboratable<I>,
+        Q: WorkQueue<PendingObligation<O>>,
+        V: VisitedSet<ty::Binder<I, ty::PredicateKind<I>>>,
+    > Iterator for Elaborator<'a, I, O, Q, V>
+{
     type Item = O;
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.stack.len(), None)
+        // Every obligation on the stack is yielded, but elaborating it may
+        // add arbitrarily many more. Once the stack is empty, we're done.
+        let upper = if self.stack.is_empty() { Some(0) } else { None };
+        (self.stack.len(), upper)
     }
 
     fn next(&mut self) -> Option<Self::Item> {
         // Extract next item from top-most stack frame, if any.
-        if let Some(obligation) = self.stack.pop() {
-            self.elaborate(&obligation);
-            Some(obligation)
-        } else {
-            None
-        }
+        let pending = self.stack.pop()?;
+        Some(self.yield_pending(pending))
     }
 }
 
+// Once the stack is empty, nothing is pushed to it again. `Elaborator::reset` consumes
+// the exhausted elaborator, so the restarted one is a new iterator as far as fusing is
+// concerned.
+impl<
+        'a,
+        I: Interner,
+        O: Elaboratable<I>,
+        Q: WorkQueue<PendingObligation<O>>,
+        V: VisitedSet<ty::Binder<I, ty::PredicateKind<I>>>,
+    > FusedIterator for Elaborator<'a, I, O, Q, V>
+{
+}
+
 ///////////////////////////////////////////////////////////////////////////
 // Supertrait iterator
 ///////////////////////////////////////////////////////////////////////////
 
-/// Computes the def-ids of the transitive supertraits of `trait_def_id`. This (intentionally)
-/// does not compute the full elaborated super-predicates but just the set of def-ids. It is used
-/// to identify which traits may define a given associated type to help avoid cycle errors,
-/// and to make size estimates for vtable layout computation.
+/// Computes the def-ids of the transitive supertraits of `trait_def_id`, starting with
+/// `trait_def_id` itself. This (intentionally) does not compute the full elaborated
+/// super-predicates but just the set of def-ids. It is used to identify which traits may
+/// define a given associated type to help avoid cycle errors, and to make size estimates
+/// for vtable layout computation.
 pub fn supertrait_def_ids<I: Interner>(
     cx: I,
     trait_def_id: I::DefId,
@@ -257,17 +1731,142 @@ pub fn supertrait_def_ids<I: Interner>(
     })
 }
 
+/// Computes the trait that is a supertrait of both `a` and `b` and is nearest to them
+/// in the supertrait graph, i.e. for which the sum of the distances from `a` and from `b`
+/// is the smallest. Each trait is considered to be a supertrait of itself. Since the
+/// supertrait graph is a DAG, there may be multiple such traits, in which case we pick
+/// the one that is discovered first when walking the supertraits of `a`.
+pub fn nearest_common_supertrait<I: Interner>(cx: I, a: I::DefId, b: I::DefId) -> Option<I::DefId> {
+    let from_a = supertrait_distances(cx, a);
+    let from_b = supertrait_distances(cx, b);
+    from_a
+        .iter()
+        .filter_map(|(&def_id, &distance)| Some((def_id, distance + *from_b.get(&def_id)?)))
+        .min_by_key(|&(_, distance)| distance)
+        .map(|(def_id, _)| def_id)
+}
+
+/// Computes the length of the shortest path from `trait_def_id` to each of its
+/// transitive supertraits, in breadth-first order.
+fn supertrait_distances<I: Interner>(cx: I, trait_def_id: I::DefId) -> IndexMap<I::DefId, usize> {
+    let mut distances: IndexMap<I::DefId, usize> = IndexMap::default();
+    distances.insert(trait_def_id, 0);
+
+    let mut next = 0;
+    while let Some((&trait_def_id, &distance)) = distances.get_index(next) {
+        for (predicate, _) in cx.explicit_super_predicates_of(trait_def_id).iter_identity() {
+            if let ty::ClauseKind::Trait(data) = predicate.kind().skip_binder() {
+                distances.entry(data.def_id()).or_insert(distance + 1);
+            }
+        }
+        next += 1;
+    }
+
+    distances
+}
+
+/// Computes a path of supertrait bounds from the trait `from` to its supertrait `to`,
+/// starting with `from` and ending with `to`, e.g. `[A, B, C]` for `trait A: B {}` and
+/// `trait B: C {}`. Returns `None` if `to` is not a supertrait of `from`.
+pub fn supertrait_path<I: Interner>(cx: I, from: I::DefId, to: I::DefId) -> Option<Vec<I::DefId>> {
+    let trait_ref = ty::Binder::dummy(ty::TraitRef::identity(cx, from));
+    let mut elaborator = elaborate_trait_ref(cx, trait_ref).filter_only_self().with_provenance();
+    let target = elaborator.by_ref().find(|pred| {
+        pred.as_clause()
+            .and_then(|clause| clause.as_trait_clause())
+            .is_some_and(|data| data.def_id() == to)
+    })?;
+
+    let steps = elaborator.provenance_of(target)?;
+    Some(steps.iter().map(|&(def_id, _)| def_id).chain([to]).collect())
+}
+
+/// The transitive supertraits of `trait_ref`, starting with `trait_ref` itself.
 pub fn supertraits<I: Interner>(
     cx: I,
     trait_ref: ty::Binder<I, ty::TraitRef<I>>,
-) -> FilterToTraits<I, Elaborator<I, I::Clause>> {
+) -> FilterToTraits<I, Elaborator<'static, I, I::Clause>> {
     elaborate(cx, [trait_ref.upcast(cx)]).filter_only_self().filter_to_traits()
 }
 
-impl<I: Interner> Elaborator<I, I::Clause> {
-    fn filter_to_traits(self) -> FilterToTraits<I, Self> {
+/// The same as [`supertraits`], named to make explicit that `trait_ref` itself is
+/// yielded as well, unlike with [`strict_supertraits`].
+pub fn supertraits_including_self<I: Interner>(
+    cx: I,
+    trait_ref: ty::Binder<I, ty::TraitRef<I>>,
+) -> FilterToTraits<I, Elaborator<'static, I, I::Clause>> {
+    supertraits(cx, trait_ref)
+}
+
+/// The transitive supertraits of `trait_ref`, without `trait_ref` itself.
+pub fn strict_supertraits<I: Interner>(
+    cx: I,
+    trait_ref: ty::Binder<I, ty::TraitRef<I>>,
+) -> impl Iterator<Item = ty::Binder<I, ty::TraitRef<I>>> {
+    elaborate(cx, [trait_ref.upcast(cx)])
+        .filter_only_self()
+        .skip_seeds()
+        .filter_map(|clause: I::Clause| clause.as_trait_clause())
+        .map(|data| data.map_bound(|t| t.trait_ref))
+}
+
+/// Like [`supertraits`], but starts from the trait ref of `trait_def_id` with the
+/// given `self_ty`, e.g. a placeholder or a trait object, and the remaining `args`,
+/// i.e. the generic args of the trait without its `Self` arg.
+pub fn supertraits_for_self<I: Interner>(
+    cx: I,
+    trait_def_id: I::DefId,
+    self_ty: I::Ty,
+    args: I::GenericArgs,
+) -> FilterToTraits<I, Elaborator<'static, I, I::Clause>> {
+    let args = std::iter::once::<I::GenericArg>(self_ty.into()).chain(args.iter());
+    supertraits(cx, ty::Binder::dummy(ty::TraitRef::new(cx, trait_def_id, args)))
+}
+
+/// The trait refs of the trait clauses and all other predicates of an elaboration,
+/// see [`Elaborator::partition_traits`].
+pub type PartitionedPredicates<I> =
+    (Vec<ty::Binder<I, ty::TraitRef<I>>>, Vec<<I as Interner>::Predicate>);
+
+impl<
+        'a,
+        I: Interner,
+        Q: WorkQueue<PendingObligation<I::Predicate>>,
+        V: VisitedSet<ty::Binder<I, ty::PredicateKind<I>>>,
+    > Elaborator<'a, I, I::Predicate, Q, V>
+{
+    /// Drives the elaborator to completion, splitting the elaborated predicates
+    /// into the trait refs of trait clauses and all other predicates.
+    pub fn partition_traits(self) -> PartitionedPredicates<I> {
+        let mut trait_refs = vec![];
+        let mut others = vec![];
+        for pred in self {
+            match pred.as_clause().and_then(|clause| clause.as_trait_clause()) {
+                Some(data) => trait_refs.push(data.map_bound(|t| t.trait_ref)),
+                None => others.push(pred),
+            }
+        }
+        (trait_refs, others)
+    }
+
+    /// Applies `f` to each elaborated predicate that is a clause, yielding the
+    /// `Some` results. Predicates that are not clauses are skipped.
+    pub fn filter_map_clauses<T, F: FnMut(I::Clause) -> Option<T>>(
+        self,
+        mut f: F,
+    ) -> impl Iterator<Item = T> + Captures<'a> {
+        self.filter_map(move |pred| pred.as_clause().and_then(&mut f))
+    }
+}
+
+impl<'a, I: Interner> Elaborator<'a, I, I::Clause> {
+    pub fn filter_to_traits(self) -> FilterToTraits<I, Self> {
         FilterToTraits { _cx: PhantomData, base_iterator: self }
     }
+
+    pub fn filter_to_traits_with_polarity(self) -> FilterToTraitsWithPolarity<I, Self> {
+        FilterToTraitsWithPolarity { _cx: PhantomData, base_iterator: self }
+    }
 }
 
 /// A filter around an iterator of predicates that makes it yield up
@@ -277,6 +1876,27 @@ pub struct FilterToTraits<I: Interner, It: Iterator<Item = I::Clause>> {
     base_iterator: It,
 }
 
+impl<I: Interner, It: Iterator<Item = I::Clause>> FilterToTraits<I, It> {
+    /// Pairs each trait ref with its self type, e.g. to group the supertraits of
+    /// several seed trait refs by the type they constrain. The self type keeps the
+    /// binder of the trait ref, as it may reference its bound vars.
+    pub fn with_self_tys(
+        self,
+    ) -> impl Iterator<Item = (ty::Binder<I, I::Ty>, ty::Binder<I, ty::TraitRef<I>>)> {
+        self.map(|trait_ref| (trait_ref.map_bound(|trait_ref| trait_ref.self_ty()), trait_ref))
+    }
+
+    /// Skips trait refs that are equal to one yielded before modulo the names of their
+    /// bound vars, even if the predicates they come from were not deduplicated, e.g.
+    /// because the elaborator compares predicates structurally.
+    pub fn dedup_trait_refs(self, cx: I) -> impl Iterator<Item = ty::Binder<I, ty::TraitRef<I>>> {
+        let mut seen = HashSet::<ty::Binder<I, ty::TraitRef<I>>>::default();
+        self.filter(move |&trait_ref| seen.insert(cx.anonymize_bound_vars(trait_ref)))
+    }
+}
+
+impl<I: Interner, It: FusedIterator<Item = I::Clause>> FusedIterator for FilterToTraits<I, It> {}
+
 impl<I: Interner, It: Iterator<Item = I::Clause>> Iterator for FilterToTraits<I, It> {
     type Item = ty::Binder<I, ty::TraitRef<I>>;
 
@@ -289,6 +1909,42 @@ fn next(&mut self) -> Option<ty::Binder<I, ty::TraitRef<I>>> {
         None
     }
 
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // We can't know how many of the remaining predicates are trait
+        // clauses without looking at them, but we do know when the base
+        // iterator is exhausted.
+        let (_, upper) = self.base_iterator.size_hint();
+        (0, upper)
+    }
+}
+
+/// Like [`FilterToTraits`], but pairs each trait reference with the polarity of
+/// the clause it was taken from, e.g. to tell negative bounds like `T: !Send`
+/// apart from positive ones. Note that negative trait clauses are yielded, but
+/// never elaborated to anything.
+pub struct FilterToTraitsWithPolarity<I: Interner, It: Iterator<Item = I::Clause>> {
+    _cx: PhantomData<I>,
+    base_iterator: It,
+}
+
+impl<I: Interner, It: FusedIterator<Item = I::Clause>> FusedIterator
+    for FilterToTraitsWithPolarity<I, It>
+{
+}
+
+impl<I: Interner, It: Iterator<Item = I::Clause>> Iterator for FilterToTraitsWithPolarity<I, It> {
+    type Item = (ty::Binder<I, ty::TraitRef<I>>, ty::PredicatePolarity);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for pred in self.base_iterator.by_ref() {
+            if let Some(data) = pred.as_trait_clause() {
+                let polarity = data.skip_binder().polarity;
+                return Some((data.map_bound(|t| t.trait_ref), polarity));
+            }
+        }
+        None
+    }
+
     fn size_hint(&self) -> (usize, Option<usize>) {
         let (_, upper) = self.base_iterator.size_hint();
         (0, upper)
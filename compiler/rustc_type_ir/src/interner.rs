@@ -257,6 +257,8 @@ fn for_each_relevant_impl(
 
     fn trait_is_fundamental(self, def_id: Self::DefId) -> bool;
 
+    fn trait_is_marker(self, trait_def_id: Self::DefId) -> bool;
+
     fn trait_may_be_implemented_via_object(self, trait_def_id: Self::DefId) -> bool;
 
     fn delay_bug(self, msg: impl ToString) -> Self::ErrorGuaranteed;
@@ -1912,6 +1912,10 @@ fn run(self, builder: &Builder<'_>) {
             cmd.arg("--with-debug-assertions");
         };
 
+        if builder.config.rust_debug_assertions {
+            cmd.arg("--with-rustc-debug-assertions");
+        }
+
         let mut llvm_components_passed = false;
         let mut copts_passed = false;
         if builder.config.llvm_enabled(compiler.host) {
@@ -143,6 +143,7 @@
     "needs-run-enabled",
     "needs-rust-lld",
     "needs-rust-lldb",
+    "needs-rustc-debug-assertions",
     "needs-sanitizer-address",
     "needs-sanitizer-cfi",
     "needs-sanitizer-dataflow",
@@ -243,6 +243,10 @@ pub struct Config {
     /// Whether to run tests with `ignore-debug` header
     pub with_debug_assertions: bool,
 
+    /// Whether the compiler was built with debug assertions, for tests with the
+    /// `needs-rustc-debug-assertions` header
+    pub with_rustc_debug_assertions: bool,
+
     /// Only run tests that match these filters
     pub filters: Vec<String>,
 
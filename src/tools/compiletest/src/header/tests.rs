@@ -69,6 +69,7 @@ struct ConfigBuilder {
     stage_id: Option<String>,
     llvm_version: Option<String>,
     git_hash: bool,
+    rustc_debug_assertions: bool,
     system_llvm: bool,
     profiler_support: bool,
 }
@@ -109,6 +110,11 @@ fn git_hash(&mut self, b: bool) -> &mut Self {
         self
     }
 
+    fn rustc_debug_assertions(&mut self, b: bool) -> &mut Self {
+        self.rustc_debug_assertions = b;
+        self
+    }
+
     fn system_llvm(&mut self, s: bool) -> &mut Self {
         self.system_llvm = s;
         self
@@ -159,6 +165,9 @@ fn build(&mut self) -> Config {
         if self.git_hash {
             args.push("--git-hash".to_owned());
         }
+        if self.rustc_debug_assertions {
+            args.push("--with-rustc-debug-assertions".to_owned());
+        }
         if self.system_llvm {
             args.push("--system-llvm".to_owned());
         }
@@ -351,6 +360,15 @@ fn git_hash() {
     assert!(!check_ignore(&config, "//@ needs-git-hash"));
 }
 
+#[test]
+fn rustc_debug_assertions() {
+    let config: Config = cfg().rustc_debug_assertions(false).build();
+    assert!(check_ignore(&config, "//@ needs-rustc-debug-assertions"));
+
+    let config: Config = cfg().rustc_debug_assertions(true).build();
+    assert!(!check_ignore(&config, "//@ needs-rustc-debug-assertions"));
+}
+
 #[test]
 fn sanitizers() {
     // Target that supports all sanitizers:
@@ -83,6 +83,11 @@ pub fn parse_config(args: Vec<String>) -> Config {
         .optopt("", "run", "whether to execute run-* tests", "auto | always | never")
         .optflag("", "ignored", "run tests marked as ignored")
         .optflag("", "with-debug-assertions", "whether to run tests with `ignore-debug` header")
+        .optflag(
+            "",
+            "with-rustc-debug-assertions",
+            "whether the compiler was built with debug assertions",
+        )
         .optmulti(
             "",
             "skip",
@@ -216,6 +221,7 @@ fn make_absolute(path: PathBuf) -> PathBuf {
     let src_base = opt_path(matches, "src-base");
     let run_ignored = matches.opt_present("ignored");
     let with_debug_assertions = matches.opt_present("with-debug-assertions");
+    let with_rustc_debug_assertions = matches.opt_present("with-rustc-debug-assertions");
     let mode = matches.opt_str("mode").unwrap().parse().expect("invalid mode");
     let has_tidy = if mode == Mode::Rustdoc {
         Command::new("tidy")
@@ -274,6 +280,7 @@ fn make_absolute(path: PathBuf) -> PathBuf {
         debugger: None,
         run_ignored,
         with_debug_assertions,
+        with_rustc_debug_assertions,
         filters,
         skip: matches.opt_strs("skip"),
         filter_exact: matches.opt_present("exact"),
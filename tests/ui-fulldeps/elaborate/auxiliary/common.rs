@@ -0,0 +1,71 @@
+//! The scaffolding shared by the elaborator tests, which include this file as a module.
+//!
+//! Each test compiles an input crate and then runs its test functions on the `TyCtxt`
+//! of that crate. The items of the input crate that a test function uses are usually
+//! placed in a module named after the test function, e.g. `partition_traits::Sub`.
+
+#![allow(dead_code)]
+
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::DefId;
+use rustc_middle::ty::TyCtxt;
+use rustc_smir::rustc_internal;
+use std::ops::ControlFlow;
+
+const CRATE_NAME: &str = "input";
+
+/// Writes `input` into a file, compiles it as a library crate, and runs each of `tests`
+/// on the result.
+pub fn run_tests(name: &str, input: &str, tests: &[fn(TyCtxt<'_>)]) {
+    run_tests_with_flags(name, input, &[], tests)
+}
+
+/// Like [`run_tests`], but passes `flags` to the compiler as well, e.g. `-Znext-solver`.
+pub fn run_tests_with_flags(name: &str, input: &str, flags: &[&str], tests: &[fn(TyCtxt<'_>)]) {
+    let path = format!("{name}_input.rs");
+    std::fs::write(&path, input).unwrap();
+    let mut args = vec![
+        "rustc".to_string(),
+        "--crate-type=lib".to_string(),
+        "--edition=2021".to_string(),
+        "--crate-name".to_string(),
+        CRATE_NAME.to_string(),
+    ];
+    args.extend(flags.iter().map(|flag| flag.to_string()));
+    args.push(path);
+    run_with_tcx!(args, |tcx| {
+        for test in tests {
+            test(tcx);
+        }
+        ControlFlow::<()>::Continue(())
+    })
+    .unwrap();
+}
+
+/// Finds the item of kind `kind` at `path` in the input crate, e.g. `partition_traits::Sub`.
+pub fn find_def(tcx: TyCtxt<'_>, kind: DefKind, path: &str) -> DefId {
+    tcx.hir_crate_items(())
+        .definitions()
+        .map(|def_id| def_id.to_def_id())
+        .find(|&def_id| tcx.def_kind(def_id) == kind && has_path(tcx, def_id, path))
+        .unwrap_or_else(|| panic!("no {kind:?} at `{path}`"))
+}
+
+/// Finds the trait at `path` in the input crate.
+pub fn find_trait(tcx: TyCtxt<'_>, path: &str) -> DefId {
+    find_def(tcx, DefKind::Trait, path)
+}
+
+/// Whether `def_id` is the item at `path` relative to the crate root.
+fn has_path(tcx: TyCtxt<'_>, mut def_id: DefId, path: &str) -> bool {
+    for name in path.rsplit("::") {
+        if tcx.opt_item_name(def_id).is_none_or(|item_name| item_name.as_str() != name) {
+            return false;
+        }
+        let Some(parent) = tcx.opt_parent(def_id) else {
+            return false;
+        };
+        def_id = parent;
+    }
+    def_id.is_crate_root()
+}
@@ -0,0 +1,138 @@
+//@ run-pass
+//! Tests of the callbacks that customize how the elaborator elaborates and yields
+//! predicates.
+
+//@ ignore-stage1
+//@ ignore-cross-compile
+//@ ignore-remote
+//@ ignore-windows-gnu mingw has troubles with linking https://github.com/rust-lang/rust/pull/116837
+//@ edition: 2021
+
+#![feature(rustc_private)]
+#![feature(control_flow_enum)]
+
+#[macro_use]
+extern crate rustc_smir;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_infer;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate stable_mir;
+
+#[path = "auxiliary/common.rs"]
+mod common;
+
+use common::find_trait;
+use rustc_infer::traits::util::elaborate;
+use rustc_middle::ty::{self, TyCtxt, Upcast};
+
+/// How often `u32: Clone` is yielded when elaborating `u32: Copy`.
+fn count_clone(tcx: TyCtxt<'_>, with_rule: bool) -> usize {
+    let copy = tcx.lang_items().copy_trait().unwrap();
+    let clone = tcx.lang_items().clone_trait().unwrap();
+    let seed: ty::Clause<'_> = ty::TraitRef::new(tcx, copy, [tcx.types.u32]).upcast(tcx);
+    let elaborator = elaborate(tcx, [seed]).with_max_depth(1).without_dedup();
+    let elaborator = if with_rule {
+        // Every `Copy` bound gets an extra `Clone` bound.
+        elaborator.with_expansion_rule(move |_, clause, children| {
+            let Some(data) = clause.as_trait_clause() else { return };
+            if data.def_id() == copy {
+                let trait_ref =
+                    data.map_bound(|data| ty::TraitRef::new(tcx, clone, [data.self_ty()]));
+                children.push(trait_ref.upcast(tcx));
+            }
+        })
+    } else {
+        elaborator
+    };
+    elaborator
+        .filter(|clause| clause.as_trait_clause().is_some_and(|data| data.def_id() == clone))
+        .count()
+}
+
+fn test_expansion_rule(tcx: TyCtxt<'_>) {
+    // Once as the supertrait of `Copy`.
+    assert_eq!(count_clone(tcx, false), 1);
+    // Once more from the rule.
+    assert_eq!(count_clone(tcx, true), 2);
+}
+
+fn test_map_clauses(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "map_clauses::Sub");
+    let base = find_trait(tcx, "map_clauses::Base");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+    // Substitute `u8` for the `T` of `Sub<T>`, keeping `Self`.
+    let identity = ty::GenericArgs::identity_for_item(tcx, sub);
+    let args = tcx.mk_args(&[identity[0], tcx.types.u8.into()]);
+
+    let mut rewritten = 0;
+    let clauses: Vec<_> = elaborate(tcx, [seed])
+        .map_clauses(|tcx, clause| {
+            rewritten += 1;
+            ty::EarlyBinder::bind(clause).instantiate(tcx, args)
+        })
+        .collect();
+    // `Self: Sup<u8>` and `Self: Base<u8>`, but not the seed `Self: Sub<T>`.
+    assert_eq!(rewritten, 2);
+    assert_eq!(clauses.len(), 3);
+    let base_ref = clauses
+        .iter()
+        .find_map(|clause| clause.as_trait_clause().filter(|data| data.def_id() == base))
+        .unwrap();
+    assert_eq!(base_ref.skip_binder().trait_ref.args.type_at(1), tcx.types.u8);
+}
+
+fn test_on_yield(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "on_yield::Sub");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+
+    let mut yielded = 0;
+    let trait_refs = elaborate(tcx, [seed]).on_yield(|_| yielded += 1).filter_to_traits().count();
+    // `Self: Sub`, `Self: Sup`, `Self: Iterator` and `Self: Base`.
+    assert_eq!(trait_refs, 4);
+    // The trait clauses, the projection bound `<Self as Iterator>::Item == u32` and
+    // `Self: 'static`.
+    assert_eq!(yielded, 6);
+}
+
+fn test_map_yield(tcx: TyCtxt<'_>) {
+    let top = find_trait(tcx, "map_yield::Top");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, top).upcast(tcx);
+
+    let mut names: Vec<_> = elaborate(tcx, [seed])
+        .map_yield(|clause| tcx.item_name(clause.as_trait_clause().unwrap().def_id()).to_string())
+        .collect();
+    names.sort();
+    // `Self: Base` is reached through both `Left` and `Right`, but only yielded once.
+    assert_eq!(names, ["Base", "Left", "Right", "Top"]);
+}
+
+fn main() {
+    common::run_tests(
+        "callbacks",
+        INPUT,
+        &[test_expansion_rule, test_map_clauses, test_on_yield, test_map_yield],
+    );
+}
+
+const INPUT: &str = r#"
+pub mod map_clauses {
+    pub trait Base<T> {}
+    pub trait Sup<T>: Base<T> {}
+    pub trait Sub<T>: Sup<T> {}
+}
+
+pub mod on_yield {
+    pub trait Base {}
+    pub trait Sup: Base + 'static {}
+    pub trait Sub: Sup + Iterator<Item = u32> {}
+}
+
+pub mod map_yield {
+    pub trait Base {}
+    pub trait Left: Base {}
+    pub trait Right: Base {}
+    pub trait Top: Left + Right {}
+}
+"#;
@@ -0,0 +1,73 @@
+//@ run-pass
+//! Tests of elaborating bounds on `#[const_trait]` traits.
+
+//@ ignore-stage1
+//@ ignore-cross-compile
+//@ ignore-remote
+//@ ignore-windows-gnu mingw has troubles with linking https://github.com/rust-lang/rust/pull/116837
+//@ edition: 2021
+
+#![feature(rustc_private)]
+#![feature(control_flow_enum)]
+
+#[macro_use]
+extern crate rustc_smir;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_infer;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate stable_mir;
+
+#[path = "auxiliary/common.rs"]
+mod common;
+
+use common::find_trait;
+use rustc_infer::traits::util::elaborate;
+use rustc_middle::ty::{self, TyCtxt, Upcast};
+
+fn test_const_trait_facets(tcx: TyCtxt<'_>) {
+    let foo = find_trait(tcx, "const_trait_facets::Foo");
+    let foo_effects = tcx.associated_type_for_effects(foo).unwrap();
+
+    for (name, requires_const_foo) in [("ConstSub", true), ("Sub", false)] {
+        let sub = find_trait(tcx, &format!("const_trait_facets::{name}"));
+        let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+        // The trait clauses are the same for both facets.
+        assert!(
+            elaborate(tcx, [seed])
+                .any(|clause| clause.as_trait_clause().is_some_and(|data| data.def_id() == foo))
+        );
+
+        // The const facet of `ConstSub` requires the const facet of `Foo`, which is
+        // recorded through the effects of `Foo` rather than in the `Self: Foo` clause.
+        let effects_min_tys = tcx.explicit_predicates_of(sub).effects_min_tys;
+        let mentions_foo_effects = effects_min_tys.iter().any(|ty| {
+            matches!(ty.kind(), ty::Alias(ty::Projection, alias) if alias.def_id == foo_effects)
+        });
+        assert_eq!(mentions_foo_effects, requires_const_foo);
+    }
+}
+
+fn main() {
+    common::run_tests_with_flags(
+        "const_traits",
+        INPUT,
+        &["-Znext-solver"],
+        &[test_const_trait_facets],
+    );
+}
+
+const INPUT: &str = r#"
+#![allow(incomplete_features)]
+#![feature(const_trait_impl, effects)]
+
+pub mod const_trait_facets {
+    #[const_trait]
+    pub trait Foo {}
+    #[const_trait]
+    pub trait ConstSub: ~const Foo {}
+    #[const_trait]
+    pub trait Sub: Foo {}
+}
+"#;
@@ -0,0 +1,284 @@
+//@ run-pass
+//! Tests of how the elaborator deduplicates predicates, and of the sets of visited
+//! predicates it is used with.
+
+//@ ignore-stage1
+//@ ignore-cross-compile
+//@ ignore-remote
+//@ ignore-windows-gnu mingw has troubles with linking https://github.com/rust-lang/rust/pull/116837
+//@ edition: 2021
+
+#![feature(rustc_private)]
+#![feature(control_flow_enum)]
+
+#[macro_use]
+extern crate rustc_smir;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_infer;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate smallvec;
+extern crate stable_mir;
+
+#[path = "auxiliary/common.rs"]
+mod common;
+
+use common::find_trait;
+use rustc_infer::traits::util::{
+    anonymize_predicate, elaborate, elaborate_delta, elaborate_with_capacity, elaborate_with_set,
+    DedupMode, ElaborateWithHashExt, PredicateSet,
+};
+use rustc_middle::ty::{self, TyCtxt, Upcast};
+use smallvec::SmallVec;
+
+/// How often `Self: Base` is yielded when elaborating `Self: Top`.
+fn count_base(tcx: TyCtxt<'_>, dedup: bool) -> usize {
+    let base = find_trait(tcx, "without_dedup::Base");
+    let top = find_trait(tcx, "without_dedup::Top");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, top).upcast(tcx);
+    let elaborator = elaborate(tcx, [seed]).with_max_depth(2);
+    let elaborator = if dedup { elaborator } else { elaborator.without_dedup() };
+    elaborator
+        .filter(|clause| clause.as_trait_clause().is_some_and(|data| data.def_id() == base))
+        .count()
+}
+
+fn test_without_dedup(tcx: TyCtxt<'_>) {
+    assert_eq!(count_base(tcx, true), 1);
+    // Once through `Left` and once through `Right`.
+    assert_eq!(count_base(tcx, false), 2);
+}
+
+/// The `u8: Tr<'_>` clause elaborated from `u8` implementing the trait named `sub`,
+/// together with its hash.
+fn hashed_tr_clause<'tcx>(tcx: TyCtxt<'tcx>, sub: &str) -> (ty::Clause<'tcx>, u64) {
+    let tr = find_trait(tcx, "with_hash::Tr");
+    let sub = find_trait(tcx, &format!("with_hash::{sub}"));
+    let seed: ty::Clause<'_> = ty::TraitRef::new(tcx, sub, [tcx.types.u8]).upcast(tcx);
+    elaborate(tcx, [seed])
+        .with_hash()
+        .find(|(clause, _)| clause.as_trait_clause().is_some_and(|data| data.def_id() == tr))
+        .unwrap()
+}
+
+fn test_with_hash(tcx: TyCtxt<'_>) {
+    let (with_a, hash_a) = hashed_tr_clause(tcx, "SubA");
+    let (with_b, hash_b) = hashed_tr_clause(tcx, "SubB");
+    // `for<'a> u8: Tr<'a>` and `for<'b> u8: Tr<'b>`.
+    assert_ne!(with_a, with_b);
+    assert_eq!(hash_a, hash_b);
+
+    let (other, other_hash) = hashed_tr_clause(tcx, "SubStatic");
+    assert_ne!(other, with_a);
+    assert_ne!(other_hash, hash_a);
+}
+
+fn test_elaborate_delta<'tcx>(tcx: TyCtxt<'tcx>) {
+    let seed = |name: &str| -> ty::Predicate<'tcx> {
+        let def_id = find_trait(tcx, &format!("elaborate_delta::{name}"));
+        ty::TraitRef::new(tcx, def_id, [tcx.types.u8]).upcast(tcx)
+    };
+    let baseline = PredicateSet::from_predicates(tcx, elaborate(tcx, [seed("B")]));
+
+    let mut delta: Vec<_> = elaborate_delta(tcx, [seed("B"), seed("C")], &baseline)
+        .map(|pred| {
+            let data = pred.as_clause().and_then(|clause| clause.as_trait_clause()).unwrap();
+            tcx.item_name(data.def_id()).to_string()
+        })
+        .collect();
+    delta.sort();
+    // `u8: B` and `u8: Base` are part of the baseline.
+    assert_eq!(delta, ["C", "Extra"]);
+}
+
+/// How many `Self: Tr<'_>` clauses are yielded when elaborating `Self: Sub<'a, 'b>` with
+/// `visited` as the set of visited predicates.
+fn count_tr<'tcx>(tcx: TyCtxt<'tcx>, visited: PredicateSet<'tcx>) -> usize {
+    let tr = find_trait(tcx, "predicate_set::Tr");
+    let sub = find_trait(tcx, "predicate_set::Sub");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+    elaborate_with_set(tcx, [seed], visited)
+        .filter(|clause| clause.as_trait_clause().is_some_and(|data| data.def_id() == tr))
+        .count()
+}
+
+fn test_predicate_set(tcx: TyCtxt<'_>) {
+    assert_eq!(count_tr(tcx, PredicateSet::new(tcx)), 2);
+    // `Self: Tr<'a>` and `Self: Tr<'b>` are the same once their regions are erased.
+    let erasing = PredicateSet::with_normalizer(tcx, |tcx, pred| tcx.erase_regions(pred));
+    assert_eq!(count_tr(tcx, erasing), 1);
+}
+
+/// The clauses elaborated from `u8: Sub` when deduplicating them with `mode`.
+fn dedup_mode_closure(tcx: TyCtxt<'_>, mode: DedupMode) -> Vec<ty::Clause<'_>> {
+    let sub = find_trait(tcx, "dedup_mode::Sub");
+    let seed: ty::Clause<'_> = ty::TraitRef::new(tcx, sub, [tcx.types.u8]).upcast(tcx);
+    elaborate(tcx, [seed]).dedup_mode(mode).collect()
+}
+
+fn test_dedup_mode(tcx: TyCtxt<'_>) {
+    // `u8: Sub`, `u8: SubA`, `u8: SubB` and `for<'a> u8: Tr<'a>`.
+    assert_eq!(dedup_mode_closure(tcx, DedupMode::Anonymized).len(), 4);
+    // As well as `for<'b> u8: Tr<'b>`.
+    let structural = dedup_mode_closure(tcx, DedupMode::Structural);
+    assert_eq!(structural.len(), 5);
+
+    let tr = find_trait(tcx, "dedup_mode::Tr");
+    let [a, b] = structural
+        .iter()
+        .filter(|clause| clause.as_trait_clause().is_some_and(|data| data.def_id() == tr))
+        .map(|clause| clause.as_predicate())
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap();
+    assert_eq!(DedupMode::Anonymized.key(tcx, a), DedupMode::Anonymized.key(tcx, b));
+    assert_ne!(DedupMode::Structural.key(tcx, a), DedupMode::Structural.key(tcx, b));
+
+    let mut set = PredicateSet::with_dedup_mode(tcx, DedupMode::Structural);
+    assert!(set.insert(a) && set.insert(b));
+    let mut set = PredicateSet::with_dedup_mode(tcx, DedupMode::Anonymized);
+    assert!(set.insert(a) && !set.insert(b));
+}
+
+fn test_drain(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "drain::Sub");
+    let seed: ty::Predicate<'_> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+    let mut set = PredicateSet::from_predicates(tcx, elaborate(tcx, [seed]));
+
+    // `Self: Sub`, `Self: SubA`, `Self: SubB` and a single `for<'a> Self: Tr<'a>`, with
+    // its bound region anonymized.
+    let drained: Vec<_> = set.drain().collect();
+    assert_eq!(drained.len(), 4);
+    assert!(drained.iter().all(|&pred| anonymize_predicate(tcx, pred) == pred));
+    assert!(!set.contains(seed));
+    assert_eq!(set.drain().count(), 0);
+
+    let predicates = PredicateSet::from_predicates(tcx, elaborate(tcx, [seed])).into_predicates();
+    assert_eq!(predicates.len(), 4);
+    assert!(drained.iter().all(|pred| predicates.contains(pred)));
+}
+
+fn test_small_vec_visited_set(tcx: TyCtxt<'_>) {
+    let top = find_trait(tcx, "small_vec_visited_set::Top");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, top).upcast(tcx);
+
+    // The shared `Self: Base` is still only yielded once, also once the inline
+    // capacity is exceeded.
+    let expected: Vec<_> = elaborate(tcx, [seed]).collect();
+    assert_eq!(expected.len(), 4);
+    type Key<'tcx> = ty::Binder<'tcx, ty::PredicateKind<'tcx>>;
+    let inline: Vec<_> = elaborate_with_set(tcx, [seed], SmallVec::<[Key<'_>; 8]>::new()).collect();
+    assert_eq!(inline, expected);
+    let spilled: Vec<_> =
+        elaborate_with_set(tcx, [seed], SmallVec::<[Key<'_>; 2]>::new()).collect();
+    assert_eq!(spilled, expected);
+}
+
+fn test_dedup_stats(tcx: TyCtxt<'_>) {
+    let top = find_trait(tcx, "dedup_stats::Top");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, top).upcast(tcx);
+
+    let mut elaborator = elaborate(tcx, [seed]);
+    assert_eq!(elaborator.dedup_stats(), (1, 0));
+    assert_eq!(elaborator.by_ref().count(), 4);
+    // `Self: Top`, `Self: Left`, `Self: Right` and `Self: Base` twice, once through
+    // each of `Left` and `Right`.
+    assert_eq!(elaborator.dedup_stats(), (5, 1));
+}
+
+fn test_elaborate_with_capacity(tcx: TyCtxt<'_>) {
+    let top = find_trait(tcx, "elaborate_with_capacity::Top");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, top).upcast(tcx);
+
+    let mut set = PredicateSet::new(tcx);
+    set.reserve(16);
+    assert!(set.capacity() >= 16);
+
+    let expected: Vec<_> = elaborate(tcx, [seed]).collect();
+    assert_eq!(elaborate_with_capacity(tcx, [seed], 16).collect::<Vec<_>>(), expected);
+    let visited = elaborate_with_capacity(tcx, [seed], 16).collect_predicate_set();
+    assert!(visited.capacity() >= 16);
+    assert!(expected.iter().all(|&clause| visited.contains(clause.as_predicate())));
+}
+
+fn main() {
+    common::run_tests(
+        "dedup",
+        INPUT,
+        &[
+            test_without_dedup,
+            test_with_hash,
+            test_elaborate_delta,
+            test_predicate_set,
+            test_dedup_mode,
+            test_drain,
+            test_small_vec_visited_set,
+            test_dedup_stats,
+            test_elaborate_with_capacity,
+        ],
+    );
+}
+
+const INPUT: &str = r#"
+pub mod without_dedup {
+    pub trait Base {}
+    pub trait Left: Base {}
+    pub trait Right: Base {}
+    pub trait Top: Left + Right {}
+}
+
+pub mod with_hash {
+    pub trait Tr<'a> {}
+    pub trait SubA: for<'a> Tr<'a> {}
+    pub trait SubB: for<'b> Tr<'b> {}
+    pub trait SubStatic: Tr<'static> {}
+}
+
+pub mod elaborate_delta {
+    pub trait Base {}
+    pub trait Extra {}
+    pub trait B: Base {}
+    pub trait C: Base + Extra {}
+}
+
+pub mod predicate_set {
+    pub trait Tr<'a> {}
+    pub trait Sub<'a, 'b>: Tr<'a> + Tr<'b> {}
+}
+
+pub mod dedup_mode {
+    pub trait Tr<'a> {}
+    pub trait SubA: for<'a> Tr<'a> {}
+    pub trait SubB: for<'b> Tr<'b> {}
+    pub trait Sub: SubA + SubB {}
+}
+
+pub mod drain {
+    pub trait Tr<'a> {}
+    pub trait SubA: for<'a> Tr<'a> {}
+    pub trait SubB: for<'b> Tr<'b> {}
+    pub trait Sub: SubA + SubB {}
+}
+
+pub mod small_vec_visited_set {
+    pub trait Base {}
+    pub trait Left: Base {}
+    pub trait Right: Base {}
+    pub trait Top: Left + Right {}
+}
+
+pub mod dedup_stats {
+    pub trait Base {}
+    pub trait Left: Base {}
+    pub trait Right: Base {}
+    pub trait Top: Left + Right {}
+}
+
+pub mod elaborate_with_capacity {
+    pub trait Base {}
+    pub trait Left: Base {}
+    pub trait Right: Base {}
+    pub trait Top: Left + Right {}
+}
+"#;
@@ -0,0 +1,108 @@
+//@ run-pass
+//! Tests of `drop_requirements`.
+
+//@ ignore-stage1
+//@ ignore-cross-compile
+//@ ignore-remote
+//@ ignore-windows-gnu mingw has troubles with linking https://github.com/rust-lang/rust/pull/116837
+//@ edition: 2021
+
+#![feature(rustc_private)]
+#![feature(control_flow_enum)]
+
+#[macro_use]
+extern crate rustc_smir;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate rustc_trait_selection;
+extern crate stable_mir;
+
+#[path = "auxiliary/common.rs"]
+mod common;
+
+use common::find_def;
+use rustc_hir::def::DefKind;
+use rustc_middle::ty::{Ty, TyCtxt};
+use rustc_trait_selection::traits::drop_requirements;
+
+/// The type that the type alias `name` stands for.
+fn alias_ty<'tcx>(tcx: TyCtxt<'tcx>, name: &str) -> Ty<'tcx> {
+    let alias = find_def(tcx, DefKind::TyAlias, &format!("drop_requirements::{name}"));
+    tcx.type_of(alias).instantiate_identity()
+}
+
+/// The type of the closure or coroutine defined in the function `name`.
+fn closure_ty<'tcx>(tcx: TyCtxt<'tcx>, name: &str) -> Ty<'tcx> {
+    let parent = find_def(tcx, DefKind::Fn, &format!("drop_requirements::{name}"));
+    let closure = tcx
+        .hir_crate_items(())
+        .definitions()
+        .map(|def_id| def_id.to_def_id())
+        .find(|&def_id| tcx.def_kind(def_id) == DefKind::Closure && tcx.parent(def_id) == parent)
+        .unwrap();
+    tcx.type_of(closure).instantiate_identity()
+}
+
+/// Whether dropping a value of type `ty` requires `u8: Clone`, which the `Drop` impls in
+/// the input crate require for `u8` fields.
+fn requires_clone<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> bool {
+    let clone = tcx.lang_items().clone_trait().unwrap();
+    drop_requirements(tcx, ty).iter().any(|clause| {
+        clause.as_trait_clause().is_some_and(|data| {
+            data.def_id() == clone && data.self_ty().skip_binder() == tcx.types.u8
+        })
+    })
+}
+
+fn test_drop_requirements(tcx: TyCtxt<'_>) {
+    assert!(requires_clone(tcx, alias_ty(tcx, "Direct")));
+    // `Vec` owns its elements through a `PhantomData`.
+    assert!(requires_clone(tcx, alias_ty(tcx, "InVec")));
+    assert!(requires_clone(tcx, closure_ty(tcx, "closure")));
+    assert!(requires_clone(tcx, closure_ty(tcx, "coroutine")));
+    // The contents of a `ManuallyDrop` are not dropped.
+    assert!(drop_requirements(tcx, alias_ty(tcx, "InManuallyDrop")).is_empty());
+    // The destructor of `Dangling` must not access its `T`, so it cannot rely on `T: Clone`.
+    assert!(!requires_clone(tcx, alias_ty(tcx, "MayDangle")));
+}
+
+fn main() {
+    common::run_tests("drop_requirements", INPUT, &[test_drop_requirements]);
+}
+
+const INPUT: &str = r#"
+#![feature(dropck_eyepatch)]
+
+pub mod drop_requirements {
+    use std::mem::ManuallyDrop;
+
+    pub struct Holder<T: Clone>(pub T);
+
+    impl<T: Clone> Drop for Holder<T> {
+        fn drop(&mut self) {}
+    }
+
+    pub struct Dangling<T: Clone>(pub T);
+
+    unsafe impl<#[may_dangle] T: Clone> Drop for Dangling<T> {
+        fn drop(&mut self) {}
+    }
+
+    pub type Direct = Holder<u8>;
+    pub type InVec = Vec<Holder<u8>>;
+    pub type InManuallyDrop = ManuallyDrop<Holder<u8>>;
+    pub type MayDangle = Dangling<u8>;
+
+    pub fn closure() {
+        let holder = Holder(0u8);
+        let _ = move || drop(holder);
+    }
+
+    pub fn coroutine() {
+        let holder = Holder(0u8);
+        let _ = async move { drop(holder) };
+    }
+}
+"#;
@@ -0,0 +1,100 @@
+//@ run-pass
+//! Tests of the opt-in elaboration rules of the elaborator.
+
+//@ ignore-stage1
+//@ ignore-cross-compile
+//@ ignore-remote
+//@ ignore-windows-gnu mingw has troubles with linking https://github.com/rust-lang/rust/pull/116837
+//@ edition: 2021
+
+#![feature(rustc_private)]
+#![feature(control_flow_enum)]
+
+#[macro_use]
+extern crate rustc_smir;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_infer;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate stable_mir;
+
+#[path = "auxiliary/common.rs"]
+mod common;
+
+use common::{find_def, find_trait};
+use rustc_hir::def::DefKind;
+use rustc_infer::traits::util::elaborate;
+use rustc_middle::ty::{self, Ty, TyCtxt, Upcast};
+
+/// How many `ConstEvaluatable` clauses are in `clauses`.
+fn count_const_evaluatable<'tcx>(clauses: impl Iterator<Item = ty::Clause<'tcx>>) -> usize {
+    clauses
+        .filter(|clause| matches!(clause.kind().skip_binder(), ty::ClauseKind::ConstEvaluatable(_)))
+        .count()
+}
+
+fn test_const_evaluatable_sub_exprs(tcx: TyCtxt<'_>) {
+    let f = find_def(tcx, DefKind::Fn, "const_evaluatable_sub_exprs::f");
+    let clauses = tcx.predicates_of(f).predicates.iter().map(|&(clause, _)| clause);
+
+    // `{ (N + 1) * 2 }: ConstEvaluatable`.
+    assert_eq!(count_const_evaluatable(elaborate(tcx, clauses.clone())), 1);
+    // As well as `N + 1`, but neither `N` nor `1`, which are not computations.
+    let elaborator = elaborate(tcx, clauses).with_const_evaluatable_sub_exprs();
+    assert_eq!(count_const_evaluatable(elaborator), 2);
+}
+
+fn test_expand_auto_traits<'tcx>(tcx: TyCtxt<'tcx>) {
+    let marker = find_trait(tcx, "expand_auto_traits::Marker");
+    let adt = |path: &str, arg: Ty<'tcx>| {
+        let def_id = find_def(tcx, DefKind::Struct, &format!("expand_auto_traits::{path}"));
+        Ty::new_adt(tcx, tcx.adt_def(def_id), tcx.mk_args(&[arg.into()]))
+    };
+    let wrap = find_def(tcx, DefKind::Struct, "expand_auto_traits::Wrap");
+    let t = ty::GenericArgs::identity_for_item(tcx, wrap).type_at(0);
+    let clause =
+        |ty: Ty<'tcx>| -> ty::Clause<'tcx> { ty::TraitRef::new(tcx, marker, [ty]).upcast(tcx) };
+    let seed = clause(adt("Wrap", t));
+
+    assert_eq!(elaborate(tcx, [seed]).collect::<Vec<_>>(), [seed]);
+    // Into the fields of `Wrap<T>` and, in turn, of `Inner<T>`, but not of
+    // `Explicit<u8>`, which implements `Marker` explicitly.
+    let clauses: Vec<_> = elaborate(tcx, [seed]).expand_auto_traits().collect();
+    let expected =
+        [seed, clause(adt("Inner", t)), clause(adt("Explicit", tcx.types.u8)), clause(t)];
+    assert_eq!(clauses.len(), expected.len(), "{clauses:?}");
+    for clause in expected {
+        assert!(clauses.contains(&clause), "{clause:?}");
+    }
+}
+
+fn main() {
+    common::run_tests(
+        "expansions",
+        INPUT,
+        &[test_const_evaluatable_sub_exprs, test_expand_auto_traits],
+    );
+}
+
+const INPUT: &str = r#"
+#![allow(incomplete_features)]
+#![feature(auto_traits)]
+#![feature(generic_const_exprs)]
+
+pub mod const_evaluatable_sub_exprs {
+    pub fn f<const N: usize>()
+    where
+        [(); (N + 1) * 2]:,
+    {
+    }
+}
+
+pub mod expand_auto_traits {
+    pub auto trait Marker {}
+    pub struct Wrap<T>(pub Inner<T>, pub Explicit<u8>);
+    pub struct Inner<T>(pub T);
+    pub struct Explicit<T>(pub T);
+    impl<T> Marker for Explicit<T> {}
+}
+"#;
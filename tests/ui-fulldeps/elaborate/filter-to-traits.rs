@@ -0,0 +1,116 @@
+//@ run-pass
+//! Tests of `FilterToTraits` and the other adapters that turn elaborated clauses
+//! into trait refs.
+
+//@ ignore-stage1
+//@ ignore-cross-compile
+//@ ignore-remote
+//@ ignore-windows-gnu mingw has troubles with linking https://github.com/rust-lang/rust/pull/116837
+//@ edition: 2021
+
+#![feature(rustc_private)]
+#![feature(control_flow_enum)]
+
+#[macro_use]
+extern crate rustc_smir;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_infer;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate stable_mir;
+
+#[path = "auxiliary/common.rs"]
+mod common;
+
+use common::find_trait;
+use rustc_infer::traits::util::{elaborate, supertraits, DedupMode};
+use rustc_middle::ty::{self, Ty, TyCtxt, Upcast};
+
+fn test_size_hint(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "size_hint::Sub");
+    let trait_ref = ty::Binder::dummy(ty::TraitRef::identity(tcx, sub));
+    // `Self: Sub`, `Self: Base` and `Self: Other`, but not `Self: 'static`.
+    let expected = 3;
+
+    let mut iter = supertraits(tcx, trait_ref);
+    assert_eq!(iter.size_hint(), (0, None));
+    for remaining in (1..=expected).rev() {
+        let (lower, upper) = iter.size_hint();
+        assert!(lower <= remaining && upper.is_none_or(|upper| remaining <= upper));
+        assert!(iter.next().is_some());
+    }
+    assert!(iter.next().is_none());
+    assert_eq!(iter.size_hint(), (0, Some(0)));
+    assert_eq!(supertraits(tcx, trait_ref).collect::<Vec<_>>().len(), expected);
+}
+
+fn test_with_self_tys(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "with_self_tys::Sub");
+    let bound_region = ty::BoundRegion { var: ty::BoundVar::ZERO, kind: ty::BrAnon };
+    let region = ty::Region::new_bound(tcx, ty::INNERMOST, bound_region);
+    let bound_vars = tcx.mk_bound_variable_kinds(&[ty::BoundVariableKind::Region(ty::BrAnon)]);
+    // `for<'a> &'a u8: Sub`.
+    let trait_ref = ty::Binder::bind_with_vars(
+        ty::TraitRef::new(tcx, sub, [Ty::new_imm_ref(tcx, region, tcx.types.u8)]),
+        bound_vars,
+    );
+
+    let pairs: Vec<_> = supertraits(tcx, trait_ref).with_self_tys().collect();
+    // `for<'a> &'a u8: Sub` and `for<'a> &'a u8: Base`.
+    assert_eq!(pairs.len(), 2);
+    for (self_ty, trait_ref) in pairs {
+        // `for<'a> &'a u8`, which references the bound region of the trait ref.
+        assert_eq!(self_ty.bound_vars(), bound_vars);
+        assert_eq!(self_ty.skip_binder(), trait_ref.skip_binder().self_ty());
+        assert!(self_ty.no_bound_vars().is_none());
+    }
+}
+
+/// How many `u8: Tr<'_>` trait refs are yielded when elaborating `u8: Sub` without
+/// anonymizing bound vars, with or without `dedup_trait_refs`.
+fn count_tr(tcx: TyCtxt<'_>, dedup_trait_refs: bool) -> usize {
+    let tr = find_trait(tcx, "dedup_trait_refs::Tr");
+    let sub = find_trait(tcx, "dedup_trait_refs::Sub");
+    let seed: ty::Clause<'_> = ty::TraitRef::new(tcx, sub, [tcx.types.u8]).upcast(tcx);
+    let trait_refs = elaborate(tcx, [seed]).dedup_mode(DedupMode::Structural).filter_to_traits();
+    if dedup_trait_refs {
+        trait_refs.dedup_trait_refs(tcx).filter(|trait_ref| trait_ref.def_id() == tr).count()
+    } else {
+        trait_refs.filter(|trait_ref| trait_ref.def_id() == tr).count()
+    }
+}
+
+fn test_dedup_trait_refs(tcx: TyCtxt<'_>) {
+    // `for<'a> u8: Tr<'a>` and `for<'b> u8: Tr<'b>`.
+    assert_eq!(count_tr(tcx, false), 2);
+    assert_eq!(count_tr(tcx, true), 1);
+}
+
+fn main() {
+    common::run_tests(
+        "filter_to_traits",
+        INPUT,
+        &[test_size_hint, test_with_self_tys, test_dedup_trait_refs],
+    );
+}
+
+const INPUT: &str = r#"
+pub mod size_hint {
+    pub trait Base {}
+    pub trait Other: 'static {}
+    pub trait Sub: Base + Other {}
+}
+
+pub mod with_self_tys {
+    pub trait Base {}
+    pub trait Sub: Base {}
+}
+
+pub mod dedup_trait_refs {
+    pub trait Tr<'a> {}
+    pub trait SubA: for<'a> Tr<'a> {}
+    pub trait SubB: for<'b> Tr<'b> {}
+    pub trait Sub: SubA + SubB {}
+}
+"#;
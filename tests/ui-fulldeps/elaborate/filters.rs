@@ -0,0 +1,390 @@
+//@ run-pass
+//! Tests of which predicates the elaborator yields and elaborates, depending on
+//! its filters and on the adapters that drive it.
+
+//@ ignore-stage1
+//@ ignore-cross-compile
+//@ ignore-remote
+//@ ignore-windows-gnu mingw has troubles with linking https://github.com/rust-lang/rust/pull/116837
+//@ edition: 2021
+
+#![feature(rustc_private)]
+#![feature(control_flow_enum)]
+
+#[macro_use]
+extern crate rustc_smir;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_infer;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate rustc_span;
+extern crate stable_mir;
+
+#[path = "auxiliary/common.rs"]
+mod common;
+
+use common::{find_def, find_trait};
+use rustc_hir::def::DefKind;
+use rustc_infer::traits::util::{
+    elaborate, elaborate_flagged, is_elaboratable_clause, try_elaborate, ClauseKindCounts,
+    ClauseKindSet, ElaborateMentioningExt, Filter, SuperTagged,
+};
+use rustc_middle::ty::{self, Ty, TyCtxt, Upcast};
+use rustc_span::ErrorGuaranteed;
+
+fn test_count_by_clause_kind(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "count_by_clause_kind::Sub");
+    let seed: ty::Predicate<'_> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+    let ambiguous: ty::Predicate<'_> = ty::PredicateKind::Ambiguous.upcast(tcx);
+
+    let counts = elaborate(tcx, [seed, ambiguous]).count_by_clause_kind();
+    // `Self: Sub`, `Self: Sup`, `Self: Iterator` and `Self: Base`, the projection bound
+    // `<Self as Iterator>::Item == u32` and `Self: 'static`. The ambiguous predicate is
+    // not a clause.
+    assert_eq!(
+        counts,
+        ClauseKindCounts {
+            trait_: 4,
+            projection: 1,
+            type_outlives: 1,
+            non_clause: 1,
+            ..Default::default()
+        }
+    );
+}
+
+fn test_only_kinds(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "only_kinds::Sub");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+
+    let elaborator =
+        elaborate(tcx, [seed]).only_kinds(ClauseKindSet::TRAIT | ClauseKindSet::PROJECTION);
+    // `Self: Sub`, `Self: Sup`, `Self: Iterator` and `Self: Base`, and the projection
+    // bound `<Self as Iterator>::Item == u32`, but not `Self: 'static`.
+    assert_eq!(
+        elaborator.count_by_clause_kind(),
+        ClauseKindCounts { trait_: 4, projection: 1, ..Default::default() }
+    );
+
+    // The projection bound is only reached through the trait clause `Self: Sub`, which is
+    // neither yielded nor elaborated.
+    let elaborator = elaborate(tcx, [seed]).only_kinds(ClauseKindSet::PROJECTION);
+    assert_eq!(elaborator.count_by_clause_kind(), ClauseKindCounts::default());
+}
+
+fn test_only_marker_supertraits(tcx: TyCtxt<'_>) {
+    let top = find_trait(tcx, "only_marker_supertraits::Top");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, top).upcast(tcx);
+    let mut names: Vec<_> = elaborate(tcx, [seed])
+        .filter_only_marker_supertraits()
+        .map(|clause| tcx.item_name(clause.as_trait_clause().unwrap().def_id()).to_string())
+        .collect();
+    names.sort();
+    // `Deep` is reached through the marker trait `Direct`, but `Hidden` is not reached
+    // through the non-marker trait `Middle`.
+    assert_eq!(names, ["Deep", "Direct", "Top"]);
+}
+
+fn test_partition_traits(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "partition_traits::Sub");
+    let seed: ty::Predicate<'_> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+
+    let (trait_refs, others) = elaborate(tcx, [seed]).partition_traits();
+    let mut traits: Vec<_> = trait_refs
+        .iter()
+        .map(|trait_ref| tcx.item_name(trait_ref.def_id()).to_string())
+        .collect();
+    traits.sort();
+    assert_eq!(traits, ["Base", "Iterator", "Sub"]);
+
+    // `Self: 'static` and `<Self as Iterator>::Item == u8`.
+    assert_eq!(others.len(), 2);
+    for pred in &others {
+        assert!(pred.as_clause().and_then(|clause| clause.as_trait_clause()).is_none());
+    }
+
+    let all: Vec<_> = elaborate(tcx, [seed]).collect();
+    assert_eq!(trait_refs.len() + others.len(), all.len());
+}
+
+/// The names of the traits of the clauses yielded by `skip_seeds` when elaborating the
+/// seeds `Self: A` and `Self: B`, sorted.
+fn skip_seeds<'tcx>(tcx: TyCtxt<'tcx>, dedup: bool) -> Vec<String> {
+    let seeds = ["skip_seeds::A", "skip_seeds::B"].map(|path| -> ty::Clause<'tcx> {
+        ty::TraitRef::identity(tcx, find_trait(tcx, path)).upcast(tcx)
+    });
+    let elaborator = elaborate(tcx, seeds).with_max_depth(2);
+    let elaborator = if dedup { elaborator } else { elaborator.without_dedup() };
+    let mut names: Vec<_> = elaborator
+        .skip_seeds()
+        .map(|clause| tcx.item_name(clause.as_trait_clause().unwrap().def_id()).to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+fn test_skip_seeds(tcx: TyCtxt<'_>) {
+    // `Self: B` is implied by `Self: A`, but as a seed it is skipped, and it is not
+    // yielded again when reached from `Self: A`. Its supertrait `Self: C` is yielded.
+    assert_eq!(skip_seeds(tcx, true), ["C"]);
+    // Without deduplication, `Self: B` is yielded once more when reached from `Self: A`,
+    // and `Self: C` once from each `Self: B`.
+    assert_eq!(skip_seeds(tcx, false), ["B", "C", "C"]);
+}
+
+fn test_is_elaboratable_clause<'tcx>(tcx: TyCtxt<'tcx>) {
+    let tr = find_trait(tcx, "is_elaboratable_clause::Tr");
+    let trait_ref = ty::TraitRef::new(tcx, tr, [tcx.types.u8]);
+    let negative = ty::TraitPredicate { trait_ref, polarity: ty::PredicatePolarity::Negative };
+    let re_static = tcx.lifetimes.re_static;
+    let type_outlives =
+        ty::ClauseKind::TypeOutlives(ty::OutlivesPredicate(tcx.types.u8, re_static));
+    let region_outlives =
+        ty::ClauseKind::RegionOutlives(ty::OutlivesPredicate(re_static, re_static));
+    let well_formed = ty::ClauseKind::WellFormed(tcx.types.u8.into());
+
+    let preds: [(ty::Predicate<'tcx>, bool); 6] = [
+        (trait_ref.upcast(tcx), true),
+        (negative.upcast(tcx), false),
+        (type_outlives.upcast(tcx), true),
+        (region_outlives.upcast(tcx), false),
+        (well_formed.upcast(tcx), false),
+        (ty::PredicateKind::Ambiguous.upcast(tcx), false),
+    ];
+    for (pred, expected) in preds {
+        assert_eq!(is_elaboratable_clause::<TyCtxt<'tcx>>(pred), expected, "{pred:?}");
+    }
+}
+
+fn test_filter_map_clauses(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "filter_map_clauses::Sub");
+    let seed: ty::Predicate<'_> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+    let ambiguous: ty::Predicate<'_> = ty::PredicateKind::Ambiguous.upcast(tcx);
+
+    // `Self: Sub`, `Self: Base` and `Self: 'static`, but not the ambiguous predicate.
+    assert_eq!(elaborate(tcx, [seed, ambiguous]).filter_map_clauses(Some).count(), 3);
+    let outlives: Vec<_> = elaborate(tcx, [seed, ambiguous])
+        .filter_map_clauses(|clause| clause.as_type_outlives_clause())
+        .collect();
+    let [outlives] = &outlives[..] else {
+        panic!("unexpected outlives clauses {outlives:?}");
+    };
+    assert_eq!(outlives.skip_binder().1, tcx.lifetimes.re_static);
+}
+
+fn test_mentioning(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "mentioning::Sub");
+    let marker = find_def(tcx, DefKind::Struct, "mentioning::Marker");
+    let other = find_trait(tcx, "mentioning::Other");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+    let mentioning = |def_id| -> Vec<String> {
+        elaborate(tcx, [seed])
+            .mentioning(def_id)
+            .map(|clause| tcx.item_name(clause.as_trait_clause().unwrap().def_id()).to_string())
+            .collect()
+    };
+
+    // `Self: Super<Marker>`, but not `Self: Base`, which is elaborated from it.
+    assert_eq!(mentioning(marker), ["Super"]);
+    assert_eq!(mentioning(other), ["Other"]);
+}
+
+fn test_filter_mode(tcx: TyCtxt<'_>) {
+    let elaborator = || elaborate(tcx, std::iter::empty::<ty::Clause<'_>>());
+    assert_eq!(elaborator().filter_mode(), Filter::All);
+    assert_eq!(elaborator().filter_only_self().filter_mode(), Filter::OnlySelf);
+    assert_eq!(
+        elaborator().filter_only_marker_supertraits().filter_mode(),
+        Filter::OnlyMarkerSupertraits
+    );
+}
+
+fn test_super_tagged(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "super_tagged::Sub");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+
+    let mut tagged: Vec<_> = elaborate(tcx, [SuperTagged::new(seed)])
+        .map(|SuperTagged { obligation, is_super }| {
+            let data = obligation.as_trait_clause().unwrap();
+            (tcx.item_name(data.def_id()).to_string(), is_super)
+        })
+        .collect();
+    tagged.sort();
+    // `Self: Base` is a supertrait bound, while `<Self as Sub>::Assoc: Other` is only
+    // implied. The seed is not elaborated from anything, so it is not tagged either.
+    assert_eq!(
+        tagged,
+        [("Base".to_string(), true), ("Other".to_string(), false), ("Sub".to_string(), false)]
+    );
+}
+
+fn test_filter_to_traits_with_polarity(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "filter_to_traits_with_polarity::Sub");
+    let tr = find_trait(tcx, "filter_to_traits_with_polarity::Tr");
+    let positive: ty::Clause<'_> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+    let trait_ref = ty::TraitRef::new(tcx, tr, [tcx.types.u8]);
+    let negative: ty::Clause<'_> =
+        ty::TraitPredicate { trait_ref, polarity: ty::PredicatePolarity::Negative }.upcast(tcx);
+    let outlives: ty::Clause<'_> =
+        ty::ClauseKind::TypeOutlives(ty::OutlivesPredicate(tcx.types.u8, tcx.lifetimes.re_static))
+            .upcast(tcx);
+
+    let mut traits: Vec<_> = elaborate(tcx, [positive, negative, outlives])
+        .filter_to_traits_with_polarity()
+        .map(|(trait_ref, polarity)| (tcx.item_name(trait_ref.def_id()).to_string(), polarity))
+        .collect();
+    traits.sort_by(|(a, _), (b, _)| a.cmp(b));
+    assert_eq!(
+        traits,
+        [
+            ("Base".to_string(), ty::PredicatePolarity::Positive),
+            ("Sub".to_string(), ty::PredicatePolarity::Positive),
+            ("Tr".to_string(), ty::PredicatePolarity::Negative),
+        ]
+    );
+}
+
+fn test_elaborate_flagged<'tcx>(tcx: TyCtxt<'tcx>) {
+    let sub = find_trait(tcx, "elaborate_flagged::Sub");
+    let seed: ty::Predicate<'tcx> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+    let ambiguous: ty::Predicate<'tcx> = ty::PredicateKind::Ambiguous.upcast(tcx);
+
+    let flagged: Vec<_> = elaborate_flagged(tcx, [seed, ambiguous]).collect();
+    // `Self: Sub`, `Self: Base` and `Self: 'static` are clauses, the ambiguous predicate
+    // is yielded, but flagged as not being one.
+    assert_eq!(flagged.len(), 4);
+    assert!(flagged.contains(&(ambiguous, false)));
+    for (pred, is_clause) in flagged {
+        assert_eq!(is_clause, pred != ambiguous, "{pred:?}");
+    }
+}
+
+fn test_try_elaborate<'tcx>(tcx: TyCtxt<'tcx>) {
+    let sub = find_trait(tcx, "try_elaborate::Sub");
+    let base = find_trait(tcx, "try_elaborate::Base");
+    // No error is emitted, so that the input crate still compiles successfully.
+    #[allow(deprecated)]
+    let guar = ErrorGuaranteed::unchecked_error_guaranteed();
+    let seed: ty::Clause<'tcx> = ty::TraitRef::new(tcx, sub, [tcx.types.u8]).upcast(tcx);
+    let erroneous: ty::Clause<'tcx> =
+        ty::TraitRef::new(tcx, sub, [Ty::new_error(tcx, guar)]).upcast(tcx);
+
+    let (ok, err): (Vec<_>, Vec<_>) =
+        try_elaborate(tcx, [seed, erroneous]).partition(|result| result.is_ok());
+    // `u8: Sub` and `u8: Base`.
+    let ok: Vec<_> = ok.into_iter().map(Result::unwrap).collect();
+    assert_eq!(ok.len(), 2);
+    assert!(ok.contains(&seed));
+    // `{type error}: Sub` and `{type error}: Base`, with their traits.
+    let err: Vec<_> = err.into_iter().map(Result::unwrap_err).collect();
+    assert_eq!(err.len(), 2);
+    assert!(err.iter().all(|error| error.guar == guar));
+    for def_id in [sub, base] {
+        assert!(err.iter().any(|error| error.def_id == Some(def_id)), "{err:?}");
+    }
+}
+
+fn main() {
+    common::run_tests(
+        "filters",
+        INPUT,
+        &[
+            test_count_by_clause_kind,
+            test_only_kinds,
+            test_only_marker_supertraits,
+            test_partition_traits,
+            test_skip_seeds,
+            test_is_elaboratable_clause,
+            test_filter_map_clauses,
+            test_mentioning,
+            test_filter_mode,
+            test_super_tagged,
+            test_filter_to_traits_with_polarity,
+            test_elaborate_flagged,
+            test_try_elaborate,
+        ],
+    );
+}
+
+const INPUT: &str = r#"
+#![feature(marker_trait_attr)]
+
+pub mod count_by_clause_kind {
+    pub trait Base {}
+    pub trait Sup: Base + 'static {}
+    pub trait Sub: Sup + Iterator<Item = u32> {}
+}
+
+pub mod only_kinds {
+    pub trait Base {}
+    pub trait Sup: Base + 'static {}
+    pub trait Sub: Sup + Iterator<Item = u32> {}
+}
+
+pub mod only_marker_supertraits {
+    #[marker]
+    pub trait Deep {}
+    #[marker]
+    pub trait Direct: Deep {}
+    #[marker]
+    pub trait Hidden {}
+    pub trait Middle: Hidden {}
+    pub trait Top: Direct + Middle {}
+}
+
+pub mod partition_traits {
+    pub trait Base: Iterator<Item = u8> {}
+    pub trait Sub: Base + 'static {}
+}
+
+pub mod skip_seeds {
+    pub trait C {}
+    pub trait B: C {}
+    pub trait A: B {}
+}
+
+pub mod is_elaboratable_clause {
+    pub trait Tr {}
+}
+
+pub mod filter_map_clauses {
+    pub trait Base {}
+    pub trait Sub: Base + 'static {}
+}
+
+pub mod mentioning {
+    pub struct Marker;
+    pub trait Base {}
+    pub trait Super<T>: Base {}
+    pub trait Other {}
+    pub trait Sub: Super<Marker> + Other {}
+}
+
+pub mod super_tagged {
+    pub trait Base {}
+    pub trait Other {}
+    pub trait Sub: Base
+    where
+        Self::Assoc: Other,
+    {
+        type Assoc;
+    }
+}
+
+pub mod filter_to_traits_with_polarity {
+    pub trait Tr {}
+    pub trait Base {}
+    pub trait Sub: Base {}
+}
+
+pub mod elaborate_flagged {
+    pub trait Base {}
+    pub trait Sub: Base + 'static {}
+}
+
+pub mod try_elaborate {
+    pub trait Base {}
+    pub trait Sub: Base {}
+}
+"#;
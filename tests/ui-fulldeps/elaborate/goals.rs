@@ -0,0 +1,78 @@
+//@ run-pass
+//! Tests of elaborating into goals of the new trait solver.
+
+//@ ignore-stage1
+//@ ignore-cross-compile
+//@ ignore-remote
+//@ ignore-windows-gnu mingw has troubles with linking https://github.com/rust-lang/rust/pull/116837
+//@ edition: 2021
+
+#![feature(rustc_private)]
+#![feature(control_flow_enum)]
+
+#[macro_use]
+extern crate rustc_smir;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_infer;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate rustc_span;
+extern crate rustc_trait_selection;
+extern crate stable_mir;
+
+#[path = "auxiliary/common.rs"]
+mod common;
+
+use common::{find_def, find_trait};
+use rustc_hir::def::DefKind;
+use rustc_infer::infer::TyCtxtInferExt;
+use rustc_infer::traits::util::elaborate;
+use rustc_middle::ty::{self, TyCtxt, Upcast};
+use rustc_span::{Span, DUMMY_SP};
+use rustc_trait_selection::solve::inspect::{InspectGoal, ProofTreeInferCtxtExt, ProofTreeVisitor};
+use rustc_trait_selection::solve::{Certainty, NoSolution};
+
+/// Records the result of the root goal of a proof tree.
+struct RootResult(Option<Result<Certainty, NoSolution>>);
+
+impl<'tcx> ProofTreeVisitor<'tcx> for RootResult {
+    fn span(&self) -> Span {
+        DUMMY_SP
+    }
+
+    fn visit_goal(&mut self, goal: &InspectGoal<'_, 'tcx>) {
+        self.0 = Some(goal.result());
+    }
+}
+
+fn test_into_goals(tcx: TyCtxt<'_>) {
+    let f = find_def(tcx, DefKind::Fn, "into_goals::f");
+    let sub = find_trait(tcx, "into_goals::Sub");
+    let param_env = tcx.param_env(f);
+    let t = ty::GenericArgs::identity_for_item(tcx, f).type_at(0);
+    let seed: ty::Clause<'_> = ty::TraitRef::new(tcx, sub, [t]).upcast(tcx);
+
+    let infcx = tcx.infer_ctxt().build();
+    let goals: Vec<_> = elaborate(tcx, [seed]).into_goals(param_env).collect();
+    // `T: Sub` and `T: Base`, each of which holds in the environment of `f`.
+    assert_eq!(goals.len(), 2);
+    for goal in goals {
+        assert_eq!(goal.param_env, param_env);
+        let mut result = RootResult(None);
+        infcx.visit_proof_tree(goal, &mut result);
+        assert_eq!(result.0, Some(Ok(Certainty::Yes)), "{goal:?}");
+    }
+}
+
+fn main() {
+    common::run_tests_with_flags("goals", INPUT, &["-Znext-solver"], &[test_into_goals]);
+}
+
+const INPUT: &str = r#"
+pub mod into_goals {
+    pub trait Base {}
+    pub trait Sub: Base {}
+    pub fn f<T: Sub>() {}
+}
+"#;
@@ -0,0 +1,133 @@
+//@ run-pass
+//! Tests of the limits on how far the elaborator elaborates.
+
+//@ ignore-stage1
+//@ ignore-cross-compile
+//@ ignore-remote
+//@ ignore-windows-gnu mingw has troubles with linking https://github.com/rust-lang/rust/pull/116837
+//@ edition: 2021
+
+#![feature(rustc_private)]
+#![feature(control_flow_enum)]
+
+#[macro_use]
+extern crate rustc_smir;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_infer;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate stable_mir;
+
+#[path = "auxiliary/common.rs"]
+mod common;
+
+use common::find_trait;
+use rustc_infer::traits::util::{elaborate, Elaborator};
+use rustc_middle::ty::{self, TyCtxt, Upcast};
+
+type ClauseElaborator<'tcx> = Elaborator<'static, TyCtxt<'tcx>, ty::Clause<'tcx>>;
+
+/// Whether elaborating `Self: A` up to `max_depth` is truncated.
+fn is_truncated(tcx: TyCtxt<'_>, max_depth: usize) -> bool {
+    let a = find_trait(tcx, "was_truncated::A");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, a).upcast(tcx);
+    let mut elaborator = elaborate(tcx, [seed]).with_max_depth(max_depth);
+    elaborator.by_ref().for_each(drop);
+    elaborator.was_truncated()
+}
+
+fn test_was_truncated(tcx: TyCtxt<'_>) {
+    // `Self: B` is not elaborated to `Self: C`.
+    assert!(is_truncated(tcx, 1));
+    // `Self: C` is not elaborated, but does not imply anything either.
+    assert!(!is_truncated(tcx, 2));
+    assert!(!is_truncated(tcx, 3));
+}
+
+/// How many supertrait clauses of `Wide` are yielded when elaborating `u32: Wide<u8>`
+/// and `u32: Wide<u16>`, and whether the elaboration was truncated.
+fn elaborate_wide<'tcx>(tcx: TyCtxt<'tcx>, budget: Option<usize>) -> (usize, bool) {
+    let wide = find_trait(tcx, "per_trait_budget::Wide");
+    let seeds = [tcx.types.u8, tcx.types.u16].map(|arg| -> ty::Clause<'tcx> {
+        ty::TraitRef::new(tcx, wide, [tcx.types.u32, arg]).upcast(tcx)
+    });
+    let elaborator = elaborate(tcx, seeds);
+    let mut elaborator = match budget {
+        Some(budget) => elaborator.with_per_trait_budget(budget),
+        None => elaborator,
+    };
+    let supertraits = elaborator
+        .by_ref()
+        .filter(|clause| clause.as_trait_clause().is_some_and(|data| data.def_id() != wide))
+        .count();
+    (supertraits, elaborator.was_truncated())
+}
+
+fn test_per_trait_budget(tcx: TyCtxt<'_>) {
+    // `u32: A`, `u32: B` and `u32: C`, which both seeds imply.
+    assert_eq!(elaborate_wide(tcx, None), (3, false));
+    // The budget is shared by both clauses of `Wide`, so after the first seed has used
+    // it up, the second one is not elaborated at all.
+    assert_eq!(elaborate_wide(tcx, Some(2)), (2, true));
+    // Predicates that are already elaborated count towards the budget as well, so the
+    // second seed is still cut off, although it does not imply any new predicates.
+    assert_eq!(elaborate_wide(tcx, Some(3)), (3, true));
+    assert_eq!(elaborate_wide(tcx, Some(6)), (3, false));
+}
+
+/// How many traits are yielded when elaborating `Self: T0` with `elaborator`, and
+/// whether the elaboration was truncated.
+fn elaborate_chain<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    elaborator: impl FnOnce(ClauseElaborator<'tcx>) -> ClauseElaborator<'tcx>,
+) -> (usize, bool) {
+    let t0 = find_trait(tcx, "recursion_limit::T0");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, t0).upcast(tcx);
+    let mut elaborator = elaborator(elaborate(tcx, [seed]));
+    let traits = elaborator.by_ref().count();
+    (traits, elaborator.was_truncated())
+}
+
+fn test_recursion_limit(tcx: TyCtxt<'_>) {
+    // `Self: T4` is at the recursion limit of the crate, so it is not elaborated.
+    assert_eq!(elaborate_chain(tcx, |elaborator| elaborator), (5, true));
+    let limited = elaborate_chain(tcx, |elaborator| elaborator.with_recursion_limit(2));
+    assert_eq!(limited, (3, true));
+    let unlimited = elaborate_chain(tcx, |elaborator| elaborator.without_recursion_limit());
+    assert_eq!(unlimited, (6, false));
+}
+
+fn main() {
+    common::run_tests(
+        "limits",
+        INPUT,
+        &[test_was_truncated, test_per_trait_budget, test_recursion_limit],
+    );
+}
+
+const INPUT: &str = r#"
+#![recursion_limit = "4"]
+
+pub mod was_truncated {
+    pub trait C {}
+    pub trait B: C {}
+    pub trait A: B {}
+}
+
+pub mod per_trait_budget {
+    pub trait A {}
+    pub trait B {}
+    pub trait C {}
+    pub trait Wide<T>: A + B + C {}
+}
+
+pub mod recursion_limit {
+    pub trait T5 {}
+    pub trait T4: T5 {}
+    pub trait T3: T4 {}
+    pub trait T2: T3 {}
+    pub trait T1: T2 {}
+    pub trait T0: T1 {}
+}
+"#;
@@ -0,0 +1,120 @@
+//@ run-pass
+//! Tests of elaborating the bounds of trait objects.
+
+//@ ignore-stage1
+//@ ignore-cross-compile
+//@ ignore-remote
+//@ ignore-windows-gnu mingw has troubles with linking https://github.com/rust-lang/rust/pull/116837
+//@ edition: 2021
+
+#![feature(rustc_private)]
+#![feature(control_flow_enum)]
+
+#[macro_use]
+extern crate rustc_smir;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_infer;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate rustc_trait_selection;
+extern crate stable_mir;
+
+#[path = "auxiliary/common.rs"]
+mod common;
+
+use common::find_trait;
+use rustc_infer::traits::util::elaborate_existential;
+use rustc_middle::ty::{self, TyCtxt};
+use rustc_trait_selection::traits::elaborated_existential_projections;
+use rustc_trait_selection::traits::vtable::upcast_target_traits;
+
+fn test_upcast_target_traits(tcx: TyCtxt<'_>) {
+    let d = find_trait(tcx, "upcast_target_traits::D");
+    let trait_ref = ty::Binder::dummy(ty::TraitRef::identity(tcx, d));
+
+    let targets: Vec<_> = upcast_target_traits(tcx, trait_ref)
+        .iter()
+        .map(|trait_ref| tcx.item_name(trait_ref.def_id()).to_string())
+        .collect();
+    // The vtable of `D` starts with the vtable of `C`, which starts with the one of `A`,
+    // its first supertrait.
+    assert_eq!(targets, ["A", "B", "C", "D"]);
+}
+
+fn test_elaborated_existential_projections(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "elaborated_existential_projections::Sub");
+    let projections = elaborated_existential_projections(tcx, ty::TraitRef::identity(tcx, sub));
+
+    // The projection bounds of both supertraits, including the one of `Base` that is only
+    // reached through `Mid`, sorted by the def-path hash of their associated item.
+    let projections: Vec<_> = projections
+        .iter()
+        .map(|pred| match pred.skip_binder() {
+            ty::ExistentialPredicate::Projection(proj) => (proj.def_id, proj.term.expect_type()),
+            pred => panic!("unexpected {pred:?}"),
+        })
+        .collect();
+    let mut names: Vec<_> =
+        projections.iter().map(|&(def_id, ty)| (tcx.item_name(def_id).to_string(), ty)).collect();
+    names.sort_by(|(a, _), (b, _)| a.cmp(b));
+    assert_eq!(names, [("Assoc".to_string(), tcx.types.u32), ("Out".to_string(), tcx.types.u8)]);
+    assert!(projections.is_sorted_by_key(|&(def_id, _)| tcx.def_path_hash(def_id)));
+}
+
+fn test_elaborate_existential(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "elaborate_existential::Sub");
+    let base = find_trait(tcx, "elaborate_existential::Base");
+    let args = tcx.mk_args(&[tcx.types.u8.into()]);
+    let principal = ty::Binder::dummy(ty::ExistentialTraitRef { def_id: sub, args });
+
+    // `dyn Sub<u8>` itself and `Base<u8>`, without a self type.
+    let expected =
+        [sub, base].map(|def_id| ty::Binder::dummy(ty::ExistentialTraitRef { def_id, args }));
+    assert_eq!(elaborate_existential(tcx, principal).collect::<Vec<_>>(), expected);
+}
+
+fn main() {
+    common::run_tests(
+        "objects",
+        INPUT,
+        &[
+            test_upcast_target_traits,
+            test_elaborated_existential_projections,
+            test_elaborate_existential,
+        ],
+    );
+}
+
+const INPUT: &str = r#"
+pub mod upcast_target_traits {
+    pub trait A {
+        fn a(&self);
+    }
+    pub trait B {
+        fn b(&self);
+    }
+    pub trait C: A + B {
+        fn c(&self);
+    }
+    pub trait D: C {
+        fn d(&self);
+    }
+}
+
+pub mod elaborated_existential_projections {
+    pub trait Base {
+        type Assoc;
+    }
+    pub trait Mid: Base<Assoc = u32> {}
+    pub trait Other {
+        type Out;
+    }
+    pub trait Sub: Mid + Other<Out = u8> {}
+}
+
+pub mod elaborate_existential {
+    pub trait Base<T> {}
+    pub trait Sub<T>: Base<T> {}
+}
+"#;
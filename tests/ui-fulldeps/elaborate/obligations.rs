@@ -0,0 +1,418 @@
+//@ run-pass
+//! Tests of the obligations the elaborator yields and of the causes and spans they
+//! carry.
+
+//@ ignore-stage1
+//@ ignore-cross-compile
+//@ ignore-remote
+//@ ignore-windows-gnu mingw has troubles with linking https://github.com/rust-lang/rust/pull/116837
+//@ edition: 2021
+
+#![feature(rustc_private)]
+#![feature(control_flow_enum)]
+
+#[macro_use]
+extern crate rustc_smir;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_infer;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate rustc_span;
+extern crate stable_mir;
+
+#[path = "auxiliary/common.rs"]
+mod common;
+
+use common::{find_def, find_trait};
+use rustc_hir::def::DefKind;
+use rustc_infer::traits::util::{
+    elaborate, elaborate_derived_impl_bounds, impl_required_supertrait_obligations,
+    transitive_bounds_with_spans, ElaborateObligationsExt, InParamEnv, SpanCollecting,
+    WithFixedCause,
+};
+use rustc_infer::traits::{Obligation, ObligationCause, ObligationCauseCode};
+use rustc_middle::ty::{self, Ty, TyCtxt, Upcast};
+use rustc_span::source_map::Spanned;
+use rustc_span::DUMMY_SP;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn test_well_formed_obligations(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "well_formed_obligations::Sub");
+    let seed: ty::Clause<'_> = ty::TraitRef::new(tcx, sub, [tcx.types.u64]).upcast(tcx);
+
+    let clauses: Vec<_> = elaborate(tcx, [seed]).with_well_formed_obligations().collect();
+    let well_formed: Vec<_> = clauses
+        .iter()
+        .filter_map(|clause| match clause.kind().skip_binder() {
+            ty::ClauseKind::WellFormed(arg) => Some(arg),
+            _ => None,
+        })
+        .collect();
+
+    let mut supertraits = 0;
+    for trait_clause in clauses.iter().filter_map(|clause| clause.as_trait_clause()) {
+        supertraits += 1;
+        for arg in trait_clause.skip_binder().trait_ref.args {
+            assert!(well_formed.contains(&arg), "missing `WellFormed({arg:?})`");
+        }
+    }
+    // `u64: Sub`, `u64: Super<u8>` and `u64: Other<u16, u32>`.
+    assert_eq!(supertraits, 3);
+    // `u64`, `u8`, `u16` and `u32`, each of them only once.
+    assert_eq!(well_formed.len(), 4);
+}
+
+fn test_outlives_cause(tcx: TyCtxt<'_>) {
+    let tr = find_trait(tcx, "outlives_cause::Tr");
+    let self_ty = Ty::new_imm_ref(tcx, tcx.lifetimes.re_static, tcx.types.u8);
+    let seed = Obligation::new(
+        tcx,
+        ObligationCause::dummy(),
+        ty::ParamEnv::empty(),
+        ty::TraitRef::new(tcx, tr, [self_ty]),
+    );
+
+    // `&'static u8: Tr` implies `&'static u8: 'static`, whose component is `'static: 'static`.
+    let obligation = elaborate(tcx, [seed])
+        .find(|obligation| {
+            obligation
+                .predicate
+                .as_clause()
+                .is_some_and(|clause| clause.as_region_outlives_clause().is_some())
+        })
+        .unwrap();
+    let ObligationCauseCode::WellFormedDerived(derived) = obligation.cause.code() else {
+        panic!("unexpected cause {:?}", obligation.cause.code());
+    };
+    assert_eq!(derived.parent_trait_pred.def_id(), tr);
+    assert!(matches!(*derived.parent_code, ObligationCauseCode::ImplDerived(_)));
+}
+
+fn test_with_usage_span(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "with_usage_span::Sub");
+    let base = find_trait(tcx, "with_usage_span::Base");
+    let usage = tcx.def_span(find_def(tcx, DefKind::Fn, "with_usage_span::usage"));
+    let seed = Obligation::new(
+        tcx,
+        ObligationCause::dummy(),
+        tcx.param_env(sub),
+        ty::TraitRef::identity(tcx, sub),
+    );
+
+    let obligation = elaborate(tcx, [seed])
+        .with_usage_span(usage)
+        .find(|obligation| {
+            obligation
+                .predicate
+                .as_clause()
+                .and_then(|clause| clause.as_trait_clause())
+                .is_some_and(|data| data.def_id() == base)
+        })
+        .unwrap();
+    assert_eq!(obligation.cause.span, usage);
+    let ObligationCauseCode::ImplDerived(cause) = obligation.cause.code() else {
+        panic!("unexpected cause {:?}", obligation.cause.code());
+    };
+    assert_eq!(tcx.sess.source_map().span_to_snippet(cause.span).unwrap(), "Base");
+}
+
+fn test_transitive_bounds_with_spans(tcx: TyCtxt<'_>) {
+    let my_ord = find_trait(tcx, "transitive_bounds_with_spans::MyOrd");
+    let seed = ty::Binder::dummy(ty::TraitRef::identity(tcx, my_ord));
+
+    let bounds: Vec<_> = transitive_bounds_with_spans(tcx, [(seed, DUMMY_SP)].into_iter())
+        .map(|(trait_ref, span)| {
+            let snippet = (!span.is_dummy())
+                .then(|| tcx.sess.source_map().span_to_snippet(span).unwrap());
+            (tcx.item_name(trait_ref.def_id()).to_string(), snippet)
+        })
+        .collect();
+    assert_eq!(
+        bounds,
+        [
+            ("MyOrd".to_string(), None),
+            ("MyPartialOrd".to_string(), Some("MyPartialOrd".to_string())),
+            ("MyPartialEq".to_string(), Some("MyPartialEq".to_string())),
+        ]
+    );
+}
+
+fn test_impl_required_supertrait_obligations(tcx: TyCtxt<'_>) {
+    let sup = find_trait(tcx, "impl_required_supertrait_obligations::Super");
+    let sub = find_trait(tcx, "impl_required_supertrait_obligations::Sub");
+
+    let impls: Vec<_> = tcx.all_impls(sub).collect();
+    assert_eq!(impls.len(), 2);
+    for impl_def_id in impls {
+        let obligations = impl_required_supertrait_obligations(tcx, impl_def_id);
+        match tcx.impl_polarity(impl_def_id) {
+            // `X: Super`.
+            ty::ImplPolarity::Positive => {
+                let [obligation] = &obligations[..] else {
+                    panic!("unexpected obligations {obligations:?}");
+                };
+                let data = obligation.predicate.as_clause().unwrap().as_trait_clause().unwrap();
+                assert_eq!(data.def_id(), sup);
+                let self_ty = tcx.type_of(impl_def_id).instantiate_identity();
+                assert_eq!(data.self_ty().skip_binder(), self_ty);
+            }
+            // `Y` does not implement `Sub`, so it does not have to implement `Super` either.
+            ty::ImplPolarity::Negative => assert!(obligations.is_empty()),
+            ty::ImplPolarity::Reservation => unreachable!(),
+        }
+    }
+
+    let module = find_def(tcx, DefKind::Mod, "impl_required_supertrait_obligations");
+    let inherent = tcx
+        .hir_crate_items(())
+        .definitions()
+        .map(|def_id| def_id.to_def_id())
+        .find(|&def_id| {
+            tcx.def_kind(def_id) == DefKind::Impl { of_trait: false }
+                && tcx.parent(def_id) == module
+        })
+        .unwrap();
+    assert!(impl_required_supertrait_obligations(tcx, inherent).is_empty());
+}
+
+fn test_rebind_param_env(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "rebind_param_env::Sub");
+    let param_env = tcx.param_env(sub);
+    let seed = Obligation::new(
+        tcx,
+        ObligationCause::dummy(),
+        ty::ParamEnv::empty(),
+        ty::TraitRef::identity(tcx, sub),
+    );
+
+    let obligations: Vec<_> = elaborate(tcx, [seed]).rebind_param_env(param_env).collect();
+    // `Self: Sub` and `Self: Base`, including the seed, which has not been yielded yet.
+    assert_eq!(obligations.len(), 2);
+    assert!(obligations.iter().all(|obligation| obligation.param_env == param_env));
+}
+
+fn test_with_fixed_cause(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "with_fixed_cause::Sub");
+    let fixed = ObligationCause::dummy_with_span(tcx.def_span(sub));
+    let seed = Obligation::new(
+        tcx,
+        ObligationCause::dummy(),
+        ty::ParamEnv::empty(),
+        ty::TraitRef::identity(tcx, sub),
+    );
+
+    let causes: Vec<_> = elaborate(tcx, [WithFixedCause(seed, fixed.clone())])
+        .map(|WithFixedCause(obligation, _)| obligation.cause)
+        .collect();
+    // `Self: Sub` keeps its own cause, while `Self: Mid` and `Self: Base` get the fixed
+    // cause instead of one derived from the obligation they are elaborated from.
+    assert_eq!(causes.len(), 3);
+    assert_eq!(causes[0], ObligationCause::dummy());
+    assert!(causes[1..].iter().all(|cause| *cause == fixed));
+}
+
+fn test_elaborate_derived_impl_bounds(tcx: TyCtxt<'_>) {
+    let clone = tcx.lang_items().clone_trait().unwrap();
+    let clone_impl = |name: &str| {
+        let adt = find_def(tcx, DefKind::Struct, &format!("elaborate_derived_impl_bounds::{name}"));
+        tcx.all_impls(clone)
+            .find(|&impl_def_id| {
+                let self_ty = tcx.type_of(impl_def_id).instantiate_identity();
+                self_ty.ty_adt_def().is_some_and(|adt_def| adt_def.did() == adt)
+            })
+            .unwrap()
+    };
+
+    let mut traits: Vec<_> = elaborate_derived_impl_bounds(tcx, clone_impl("Derived"))
+        .unwrap()
+        .filter_map(|clause| clause.as_trait_clause())
+        .map(|data| tcx.item_name(data.def_id()).to_string())
+        .collect();
+    traits.sort();
+    // The `T: Clone` bound added by the derive, the `T: Sub` and `T: Sized` bounds of the
+    // struct, and the supertraits of `Clone` and `Sub`.
+    assert_eq!(traits, ["Base", "Clone", "Sized", "Sub"]);
+
+    assert!(elaborate_derived_impl_bounds(tcx, clone_impl("Manual")).is_none());
+}
+
+fn test_in_param_env(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "in_param_env::Sub");
+    let param_env = tcx.param_env(sub);
+    let seed = Obligation::new(
+        tcx,
+        ObligationCause::dummy(),
+        ty::ParamEnv::empty(),
+        ty::TraitRef::identity(tcx, sub),
+    );
+
+    let obligations: Vec<_> = elaborate(tcx, [InParamEnv(seed, param_env)])
+        .map(|InParamEnv(obligation, _)| obligation)
+        .collect();
+    // The seed keeps its own param-env, only `Self: Base` is in the override.
+    let [seed, base] = &obligations[..] else { panic!("{obligations:?}") };
+    assert_eq!(seed.param_env, ty::ParamEnv::empty());
+    assert_eq!(base.param_env, param_env);
+}
+
+fn test_span_tracking(tcx: TyCtxt<'_>) {
+    let [top, left, right, base] = ["Top", "Left", "Right", "Base"]
+        .map(|name| find_trait(tcx, &format!("span_tracking::{name}")));
+    let pred = |def_id| -> ty::Predicate<'_> { ty::TraitRef::identity(tcx, def_id).upcast(tcx) };
+    let mut elaborator = elaborate(tcx, [pred(top)]).with_span_tracking();
+    assert_eq!(elaborator.by_ref().count(), 4);
+
+    // `Self: Base` is only yielded once, but both of its bounds are recorded.
+    let base_spans = elaborator.spans_of(pred(base));
+    assert_eq!(base_spans.len(), 2);
+    assert_ne!(base_spans[0], base_spans[1]);
+    for &span in base_spans {
+        assert_eq!(tcx.sess.source_map().span_to_snippet(span).unwrap(), "Base");
+    }
+    assert_eq!(elaborator.spans_of(pred(left)).len(), 1);
+    assert_eq!(elaborator.spans_of(pred(right)).len(), 1);
+    assert!(elaborator.spans_of(pred(top)).is_empty());
+}
+
+fn test_span_collecting(tcx: TyCtxt<'_>) {
+    let top = find_trait(tcx, "span_collecting::Top");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, top).upcast(tcx);
+    let spans = Rc::new(RefCell::new(vec![]));
+
+    let elaborated = elaborate(tcx, [SpanCollecting::new(seed, spans.clone())]).count();
+    assert_eq!(elaborated, 4);
+    // The bounds of `Top`, and the `Base` bounds of both `Left` and `Right`.
+    let mut snippets: Vec<_> = spans
+        .borrow()
+        .iter()
+        .map(|&span| tcx.sess.source_map().span_to_snippet(span).unwrap())
+        .collect();
+    snippets.sort();
+    assert_eq!(snippets, ["Base", "Base", "Left", "Right"]);
+}
+
+fn test_spanned(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "spanned::Sub");
+    let span = tcx.def_span(sub);
+    let seed = Spanned { node: ty::TraitRef::identity(tcx, sub).upcast(tcx), span };
+
+    let elaborated: Vec<Spanned<ty::Clause<'_>>> = elaborate(tcx, [seed]).collect();
+    // `Self: Sub`, `Self: Base` and `Self: 'static`, all with the span of the seed.
+    assert_eq!(elaborated.len(), 3);
+    assert!(elaborated.iter().all(|spanned| spanned.span == span));
+}
+
+fn main() {
+    common::run_tests(
+        "obligations",
+        INPUT,
+        &[
+            test_well_formed_obligations,
+            test_outlives_cause,
+            test_with_usage_span,
+            test_transitive_bounds_with_spans,
+            test_impl_required_supertrait_obligations,
+            test_rebind_param_env,
+            test_with_fixed_cause,
+            test_elaborate_derived_impl_bounds,
+            test_in_param_env,
+            test_span_tracking,
+            test_span_collecting,
+            test_spanned,
+        ],
+    );
+}
+
+const INPUT: &str = r#"
+#![feature(negative_impls)]
+
+pub mod well_formed_obligations {
+    pub trait Super<T> {}
+    pub trait Other<T, U> {}
+    pub trait Sub: Super<u8> + Other<u16, u32> {}
+}
+
+pub mod outlives_cause {
+    pub trait Tr: 'static {}
+}
+
+pub mod with_usage_span {
+    pub trait Base {}
+    pub trait Sub: Base {}
+    pub fn usage<T: Sub>() {}
+}
+
+pub mod transitive_bounds_with_spans {
+    pub trait MyPartialEq {}
+    pub trait MyPartialOrd: MyPartialEq {}
+    pub trait MyOrd: MyPartialOrd + 'static {}
+}
+
+pub mod impl_required_supertrait_obligations {
+    pub trait Super {}
+    pub trait Sub: Super {}
+
+    pub struct X;
+    impl Super for X {}
+    impl Sub for X {}
+
+    impl X {
+        pub fn inherent() {}
+    }
+
+    pub struct Y;
+    impl !Sub for Y {}
+}
+
+pub mod rebind_param_env {
+    pub trait Base {}
+    pub trait Sub: Base {}
+}
+
+pub mod with_fixed_cause {
+    pub trait Base {}
+    pub trait Mid: Base {}
+    pub trait Sub: Mid {}
+}
+
+pub mod elaborate_derived_impl_bounds {
+    pub trait Base {}
+    pub trait Sub: Base {}
+
+    #[derive(Clone)]
+    pub struct Derived<T: Sub>(T);
+
+    pub struct Manual<T>(T);
+    impl<T: Sub> Clone for Manual<T> {
+        fn clone(&self) -> Self {
+            unimplemented!()
+        }
+    }
+}
+
+pub mod in_param_env {
+    pub trait Base {}
+    pub trait Sub: Base {}
+}
+
+pub mod span_tracking {
+    pub trait Base {}
+    pub trait Left: Base {}
+    pub trait Right: Base {}
+    pub trait Top: Left + Right {}
+}
+
+pub mod span_collecting {
+    pub trait Base {}
+    pub trait Left: Base {}
+    pub trait Right: Base {}
+    pub trait Top: Left + Right {}
+}
+
+pub mod spanned {
+    pub trait Base {}
+    pub trait Sub: Base + 'static {}
+}
+"#;
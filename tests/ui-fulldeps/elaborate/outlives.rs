@@ -0,0 +1,204 @@
+//@ run-pass
+//! Tests of elaborating outlives bounds into the outlives bounds of their components.
+
+//@ ignore-stage1
+//@ ignore-cross-compile
+//@ ignore-remote
+//@ ignore-windows-gnu mingw has troubles with linking https://github.com/rust-lang/rust/pull/116837
+//@ edition: 2021
+
+#![feature(rustc_private)]
+#![feature(control_flow_enum)]
+
+#[macro_use]
+extern crate rustc_smir;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_infer;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate rustc_span;
+extern crate rustc_trait_selection;
+extern crate stable_mir;
+
+#[path = "auxiliary/common.rs"]
+mod common;
+
+use common::{find_def, find_trait};
+use rustc_hir::def::DefKind;
+use rustc_infer::infer::{DefineOpaqueTypes, TyCtxtInferExt};
+use rustc_infer::traits::util::{
+    elaborate, elaborate_in_infcx, elaborated_region_bound_pairs, outlives_components_as_clauses,
+};
+use rustc_infer::traits::ObligationCause;
+use rustc_middle::ty::{self, Ty, TyCtxt, Upcast};
+use rustc_span::DUMMY_SP;
+use rustc_trait_selection::traits::elaborate_normalizing_aliases;
+
+fn test_elaborated_region_bound_pairs(tcx: TyCtxt<'_>) {
+    let s = find_def(tcx, DefKind::Struct, "elaborated_region_bound_pairs::S");
+    let tr = find_trait(tcx, "elaborated_region_bound_pairs::Tr");
+    let args = ty::GenericArgs::identity_for_item(tcx, s);
+    let (a, b, t) = (args.region_at(0), args.region_at(1), args.type_at(2));
+    let ref_ty = Ty::new_imm_ref(tcx, a, t);
+
+    let outlives: ty::Clause<'_> =
+        ty::ClauseKind::TypeOutlives(ty::OutlivesPredicate(ref_ty, b)).upcast(tcx);
+    let trait_clause: ty::Clause<'_> = ty::TraitRef::new(tcx, tr, [t]).upcast(tcx);
+
+    let pairs = elaborated_region_bound_pairs(tcx, [outlives, trait_clause]);
+    // `&'a T: 'b`, `'a: 'b` and `T: 'b`, but not `T: Tr`.
+    assert_eq!(pairs.len(), 3);
+    for arg in [ty::GenericArg::from(ref_ty), a.into(), t.into()] {
+        assert!(pairs.contains(&ty::OutlivesPredicate(arg, b)), "{arg:?}: {b:?}");
+    }
+}
+
+fn test_outlives_components_as_clauses<'tcx>(tcx: TyCtxt<'tcx>) {
+    let s = find_def(tcx, DefKind::Struct, "outlives_components_as_clauses::S");
+    let assoc = find_def(tcx, DefKind::AssocTy, "outlives_components_as_clauses::Tr::Assoc");
+    let args = ty::GenericArgs::identity_for_item(tcx, s);
+    let (a, b, t) = (args.region_at(0), args.region_at(1), args.type_at(2));
+    let components = |ty: Ty<'tcx>| outlives_components_as_clauses(tcx, ty, b).to_vec();
+    let region_outlives = |r| ty::ClauseKind::RegionOutlives(ty::OutlivesPredicate(r, b));
+    let type_outlives = |ty| ty::ClauseKind::TypeOutlives(ty::OutlivesPredicate(ty, b));
+
+    assert_eq!(components(Ty::new_imm_ref(tcx, a, tcx.types.u8)), [region_outlives(a)]);
+    assert_eq!(components(t), [type_outlives(t)]);
+    let placeholder = Ty::new_placeholder(
+        tcx,
+        ty::Placeholder {
+            universe: ty::UniverseIndex::from_u32(1),
+            bound: ty::BoundTy { var: ty::BoundVar::ZERO, kind: ty::BoundTyKind::Anon },
+        },
+    );
+    assert_eq!(components(placeholder), [type_outlives(placeholder)]);
+    let projection = Ty::new_projection(tcx, assoc, [t]);
+    assert_eq!(components(projection), [type_outlives(projection)]);
+
+    // Unresolved inference variables, bound regions and aliases with escaping bound
+    // vars, here `<&'^0 T as Tr>::Assoc`, are skipped.
+    assert!(components(Ty::new_var(tcx, ty::TyVid::from_u32(0))).is_empty());
+    let bound_region = ty::BoundRegion { var: ty::BoundVar::ZERO, kind: ty::BrAnon };
+    let bound = ty::Region::new_bound(tcx, ty::INNERMOST, bound_region);
+    assert!(components(Ty::new_imm_ref(tcx, bound, tcx.types.u8)).is_empty());
+    let escaping = Ty::new_projection(tcx, assoc, [Ty::new_imm_ref(tcx, bound, t)]);
+    assert!(components(escaping).is_empty());
+}
+
+fn test_elaborate_in_infcx<'tcx>(tcx: TyCtxt<'tcx>) {
+    let s = find_def(tcx, DefKind::Struct, "elaborate_in_infcx::S");
+    let args = ty::GenericArgs::identity_for_item(tcx, s);
+    let (a, t) = (args.region_at(0), args.type_at(1));
+    let infcx = tcx.infer_ctxt().build();
+    let var = infcx.next_ty_var(DUMMY_SP);
+    let seed: ty::Clause<'tcx> =
+        ty::ClauseKind::TypeOutlives(ty::OutlivesPredicate(Ty::new_imm_ref(tcx, a, var), a))
+            .upcast(tcx);
+    let mentions_t_outlives = |clauses: Vec<ty::Clause<'tcx>>| {
+        clauses.contains(&ty::ClauseKind::TypeOutlives(ty::OutlivesPredicate(t, a)).upcast(tcx))
+    };
+
+    infcx
+        .at(&ObligationCause::dummy(), tcx.param_env(s))
+        .eq(DefineOpaqueTypes::Yes, var, t)
+        .unwrap();
+    // Without the inference context, `?0: 'a` is dropped as it is unresolved, even
+    // though `?0` has been unified with `T`.
+    assert!(!mentions_t_outlives(elaborate(tcx, [seed]).collect()));
+    assert!(mentions_t_outlives(elaborate_in_infcx(&infcx, [seed]).collect()));
+}
+
+fn test_elaborate_normalizing_aliases<'tcx>(tcx: TyCtxt<'tcx>) {
+    let s = find_def(tcx, DefKind::Struct, "elaborate_normalizing_aliases::S");
+    let assoc = find_def(tcx, DefKind::AssocTy, "elaborate_normalizing_aliases::Tr::Assoc");
+    let a = ty::GenericArgs::identity_for_item(tcx, s).region_at(0);
+    let alias = Ty::new_projection(tcx, assoc, [tcx.types.u8]);
+    let seed: ty::Clause<'tcx> =
+        ty::ClauseKind::TypeOutlives(ty::OutlivesPredicate(alias, a)).upcast(tcx);
+    let static_outlives: ty::Clause<'tcx> =
+        ty::ClauseKind::RegionOutlives(ty::OutlivesPredicate(tcx.lifetimes.re_static, a))
+            .upcast(tcx);
+
+    // `<u8 as Tr>::Assoc` normalizes to `&'static u16`, so `'static: 'a` is implied.
+    let infcx = tcx.infer_ctxt().build();
+    let clauses = elaborate_normalizing_aliases(&infcx, tcx.param_env(s), [seed]);
+    assert_eq!(clauses, [seed, static_outlives]);
+    assert_eq!(elaborate(tcx, [seed]).collect::<Vec<_>>(), [seed]);
+}
+
+fn test_into_region_bound_pairs<'tcx>(tcx: TyCtxt<'tcx>) {
+    let s = find_def(tcx, DefKind::Struct, "into_region_bound_pairs::S");
+    let tr = find_trait(tcx, "into_region_bound_pairs::Tr");
+    let args = ty::GenericArgs::identity_for_item(tcx, s);
+    let (a, b, t) = (args.region_at(0), args.region_at(1), args.type_at(2));
+    let ref_ty = Ty::new_imm_ref(tcx, a, t);
+    let outlives: ty::Clause<'tcx> =
+        ty::ClauseKind::TypeOutlives(ty::OutlivesPredicate(ref_ty, b)).upcast(tcx);
+    let trait_clause: ty::Clause<'tcx> = ty::TraitRef::new(tcx, tr, [t]).upcast(tcx);
+    let bound_region = ty::BoundRegion { var: ty::BoundVar::ZERO, kind: ty::BrAnon };
+    let bound = ty::Region::new_bound(tcx, ty::INNERMOST, bound_region);
+    // `for<'x> &'x u8: 'b`.
+    let higher_ranked: ty::Clause<'tcx> = ty::Binder::bind_with_vars(
+        ty::ClauseKind::TypeOutlives(ty::OutlivesPredicate(
+            Ty::new_imm_ref(tcx, bound, tcx.types.u8),
+            b,
+        )),
+        tcx.mk_bound_variable_kinds(&[ty::BoundVariableKind::Region(ty::BrAnon)]),
+    )
+    .upcast(tcx);
+
+    let pairs = elaborate(tcx, [outlives, trait_clause, higher_ranked]).into_region_bound_pairs();
+    // `&'a T: 'b`, `'a: 'b` and `T: 'b`, but neither `T: Tr` nor `for<'x> &'x u8: 'b`.
+    assert_eq!(pairs.len(), 3);
+    for arg in [ty::GenericArg::from(ref_ty), a.into(), t.into()] {
+        assert!(pairs.contains(&ty::OutlivesPredicate(arg, b)), "{arg:?}: {b:?}");
+    }
+}
+
+fn main() {
+    common::run_tests(
+        "outlives",
+        INPUT,
+        &[
+            test_elaborated_region_bound_pairs,
+            test_outlives_components_as_clauses,
+            test_elaborate_in_infcx,
+            test_elaborate_normalizing_aliases,
+            test_into_region_bound_pairs,
+        ],
+    );
+}
+
+const INPUT: &str = r#"
+pub mod elaborated_region_bound_pairs {
+    pub trait Tr {}
+    pub struct S<'a, 'b, T>(&'a &'b T);
+}
+
+pub mod outlives_components_as_clauses {
+    pub trait Tr {
+        type Assoc;
+    }
+    pub struct S<'a, 'b, T>(&'a &'b T);
+}
+
+pub mod elaborate_in_infcx {
+    pub struct S<'a, T>(&'a T);
+}
+
+pub mod elaborate_normalizing_aliases {
+    pub trait Tr {
+        type Assoc;
+    }
+    impl Tr for u8 {
+        type Assoc = &'static u16;
+    }
+    pub struct S<'a>(&'a ());
+}
+
+pub mod into_region_bound_pairs {
+    pub trait Tr {}
+    pub struct S<'a, 'b, T>(&'a &'b T);
+}
+"#;
@@ -0,0 +1,64 @@
+//@ run-pass
+//! Tests of rendering elaboration as a Graphviz graph, which is only available
+//! with debug assertions.
+
+//@ ignore-stage1
+//@ ignore-cross-compile
+//@ ignore-remote
+//@ ignore-windows-gnu mingw has troubles with linking https://github.com/rust-lang/rust/pull/116837
+//@ needs-rustc-debug-assertions
+//@ edition: 2021
+
+#![feature(rustc_private)]
+#![feature(control_flow_enum)]
+
+#[macro_use]
+extern crate rustc_smir;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_infer;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate stable_mir;
+
+#[path = "auxiliary/common.rs"]
+mod common;
+
+use common::find_trait;
+use rustc_infer::traits::util::{elaborate, ElaborateDebugExt};
+use rustc_infer::traits::{Obligation, ObligationCause};
+use rustc_middle::ty::{self, TyCtxt, Upcast};
+
+fn test_render_graph(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "render_graph::Sub");
+    let base = find_trait(tcx, "render_graph::Base");
+    let seed = Obligation::new(
+        tcx,
+        ObligationCause::dummy(),
+        tcx.param_env(sub),
+        ty::TraitRef::identity(tcx, sub),
+    );
+    let sub_pred: ty::Predicate<'_> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+    let base_pred: ty::Predicate<'_> = ty::TraitRef::identity(tcx, base).upcast(tcx);
+
+    let dot = elaborate(tcx, [seed]).render_graph();
+    assert!(dot.starts_with("digraph elaboration {\n"), "{dot}");
+    assert!(dot.ends_with("}\n"), "{dot}");
+    for node in [sub_pred, base_pred] {
+        assert!(dot.contains(&format!("    \"{node}\";\n")), "{dot}");
+    }
+    // The single edge from `Self: Sub` to its first super-predicate `Self: Base`.
+    assert_eq!(dot.matches(" -> ").count(), 1, "{dot}");
+    assert!(dot.contains(&format!("[label=\"{}#0\"];", tcx.def_path_str(sub))), "{dot}");
+}
+
+fn main() {
+    common::run_tests("render_graph", INPUT, &[test_render_graph]);
+}
+
+const INPUT: &str = r#"
+pub mod render_graph {
+    pub trait Base {}
+    pub trait Sub: Base {}
+}
+"#;
@@ -0,0 +1,385 @@
+//@ run-pass
+//! Tests of the helpers that elaborate the supertraits of trait refs.
+
+//@ ignore-stage1
+//@ ignore-cross-compile
+//@ ignore-remote
+//@ ignore-windows-gnu mingw has troubles with linking https://github.com/rust-lang/rust/pull/116837
+//@ edition: 2021
+
+#![feature(rustc_private)]
+#![feature(control_flow_enum)]
+
+#[macro_use]
+extern crate rustc_smir;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_infer;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate rustc_span;
+extern crate stable_mir;
+
+#[path = "auxiliary/common.rs"]
+mod common;
+
+use common::find_trait;
+use rustc_infer::traits::util::{
+    common_supertrait, elaborate, elaborate_trait_ref, elaborated_clauses_interned,
+    nearest_common_supertrait, strict_supertraits, supertrait_must_implement_one_of,
+    supertrait_path, supertraits, supertraits_for_self, supertraits_including_self,
+    transitive_bounds_unique_traits,
+};
+use rustc_middle::ty::{self, TyCtxt, Upcast};
+use rustc_span::sym;
+
+fn test_transitive_bounds_unique_traits(tcx: TyCtxt<'_>) {
+    let foo = find_trait(tcx, "transitive_bounds_unique_traits::Foo");
+    let sub = find_trait(tcx, "transitive_bounds_unique_traits::Sub");
+    let seed = ty::Binder::dummy(ty::TraitRef::identity(tcx, sub));
+
+    let trait_refs: Vec<_> = transitive_bounds_unique_traits(tcx, [seed].into_iter()).collect();
+    let mut traits: Vec<_> =
+        trait_refs.iter().map(|trait_ref| tcx.item_name(trait_ref.def_id()).to_string()).collect();
+    traits.sort();
+    // Only one of `Self: Foo<u32>` and `Self: Foo<i32>`, and not `Self: 'static`.
+    assert_eq!(traits, ["Foo", "Sub"]);
+
+    // The one that is kept is the one that is elaborated first.
+    let first_foo = supertraits(tcx, seed).find(|trait_ref| trait_ref.def_id() == foo);
+    assert_eq!(trait_refs.iter().find(|trait_ref| trait_ref.def_id() == foo), first_foo.as_ref());
+}
+
+fn test_supertrait_must_implement_one_of(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "supertrait_must_implement_one_of::Sub");
+    let seed = ty::Binder::dummy(ty::TraitRef::identity(tcx, sub));
+
+    let mut traits: Vec<_> = supertraits(tcx, seed)
+        .map(|trait_ref| tcx.item_name(trait_ref.def_id()).to_string())
+        .collect();
+    traits.sort();
+    assert_eq!(traits, ["Equal", "Sub", "Super", "Toggle"]);
+
+    let mut groups: Vec<_> = supertrait_must_implement_one_of(tcx, sub)
+        .map(|(def_id, group)| {
+            let methods: Vec<_> = group.iter().map(|ident| ident.as_str()).collect();
+            format!("{}({})", tcx.item_name(def_id), methods.join(", "))
+        })
+        .collect();
+    groups.sort();
+    // `Super` and `Sub` have no such attribute.
+    assert_eq!(groups, ["Equal(eq, neq)", "Toggle(on, off)"]);
+}
+
+fn test_elaborated_clauses_interned(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "elaborated_clauses_interned::Sub");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+
+    let clauses = elaborated_clauses_interned(tcx, [seed, seed]);
+    // `Self: Sub`, `Self: Base` and `Self: 'static`, with the repeated seed only once.
+    assert_eq!(clauses.len(), 3);
+    assert_eq!(clauses.to_vec(), elaborate(tcx, [seed]).collect::<Vec<_>>());
+    // The same closure is interned to the same list.
+    assert_eq!(clauses, elaborated_clauses_interned(tcx, [seed]));
+}
+
+fn test_nearest_common_supertrait(tcx: TyCtxt<'_>) {
+    let [root, mid, left, right, other] = ["Root", "Mid", "Left", "Right", "Other"]
+        .map(|name| find_trait(tcx, &format!("nearest_common_supertrait::{name}")));
+    // `Root` is a common supertrait as well, but further away.
+    assert_eq!(nearest_common_supertrait(tcx, left, right), Some(mid));
+    assert_eq!(nearest_common_supertrait(tcx, right, left), Some(mid));
+    assert_eq!(nearest_common_supertrait(tcx, left, mid), Some(mid));
+    assert_eq!(nearest_common_supertrait(tcx, left, left), Some(left));
+    assert_eq!(nearest_common_supertrait(tcx, mid, root), Some(root));
+    assert_eq!(nearest_common_supertrait(tcx, left, other), None);
+}
+
+fn test_elaborate_trait_ref(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "elaborate_trait_ref::Sub");
+    let trait_ref = ty::Binder::dummy(ty::TraitRef::identity(tcx, sub));
+
+    let preds: Vec<_> = elaborate_trait_ref(tcx, trait_ref).collect();
+    // `Self: Sub`, `Self: Base` and `Self: 'static`.
+    assert_eq!(preds.len(), 3);
+    let trait_refs: Vec<_> = preds
+        .iter()
+        .filter_map(|pred| pred.as_clause()?.as_trait_clause())
+        .map(|data| data.map_bound(|data| data.trait_ref))
+        .collect();
+    assert_eq!(trait_refs, supertraits(tcx, trait_ref).collect::<Vec<_>>());
+}
+
+fn test_reaches_trait(tcx: TyCtxt<'_>) {
+    let [sub, base, other, unrelated] = ["Sub", "Base", "Other", "Unrelated"]
+        .map(|name| find_trait(tcx, &format!("reaches_trait::{name}")));
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+
+    assert!(elaborate(tcx, [seed]).reaches_trait(sub));
+    assert!(elaborate(tcx, [seed]).reaches_trait(base));
+    assert!(!elaborate(tcx, [seed]).reaches_trait(unrelated));
+    // `Self: Sub` implies `<Self as Sub>::Assoc: Other`, but `Other` is not a supertrait.
+    assert!(
+        elaborate(tcx, [seed])
+            .any(|clause| clause.as_trait_clause().is_some_and(|data| data.def_id() == other))
+    );
+    assert!(!elaborate(tcx, [seed]).reaches_trait(other));
+}
+
+fn test_supertraits_cached(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "supertraits_cached::Sub");
+
+    let cached: Vec<_> = tcx.supertraits_cached(sub).collect();
+    // `Sub`, `Left`, `Right` and `Base`, which is only yielded once.
+    assert_eq!(cached.len(), 4);
+    assert_eq!(cached, tcx.supertrait_def_ids(sub).collect::<Vec<_>>());
+    // The set is computed once and then shared.
+    assert!(std::ptr::eq(tcx.supertrait_def_ids_closure(sub), tcx.supertrait_def_ids_closure(sub)));
+}
+
+fn test_supertrait_path(tcx: TyCtxt<'_>) {
+    let [a, b, c, unrelated] = ["A", "B", "C", "Unrelated"]
+        .map(|name| find_trait(tcx, &format!("supertrait_path::{name}")));
+
+    assert_eq!(supertrait_path(tcx, a, c), Some(vec![a, b, c]));
+    assert_eq!(supertrait_path(tcx, a, a), Some(vec![a]));
+    // `C` is not a subtrait of `A`, and `Unrelated` is only a bound on the associated type.
+    assert_eq!(supertrait_path(tcx, c, a), None);
+    assert_eq!(supertrait_path(tcx, a, unrelated), None);
+}
+
+fn test_supertrait_def_ids(tcx: TyCtxt<'_>) {
+    let ord = tcx.get_diagnostic_item(sym::Ord).unwrap();
+    let def_ids: Vec<_> = tcx.supertrait_def_ids(ord).collect();
+    // `Ord` itself comes first, then its supertraits in an unspecified order.
+    assert_eq!(def_ids[0], ord);
+    let mut names: Vec<_> = def_ids.iter().map(|&def_id| tcx.item_name(def_id)).collect();
+    names.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    assert_eq!(names, [sym::Eq, sym::Ord, sym::PartialEq, sym::PartialOrd]);
+}
+
+fn test_supertraits_of_trait_ref(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "supertraits_of_trait_ref::Sub");
+    let trait_ref = ty::Binder::dummy(ty::TraitRef::new(tcx, sub, [tcx.types.u8, tcx.types.u16]));
+
+    let cached = tcx.supertraits_of_trait_ref(trait_ref);
+    // `u8: Sub<u16>`, `u8: Base<u16>` and `u8: Base<u8>`, with their generic args.
+    assert_eq!(cached.len(), 3);
+    assert_eq!(cached, supertraits(tcx, trait_ref).collect::<Vec<_>>());
+    // The supertraits are computed once and then shared.
+    assert!(std::ptr::eq(cached, tcx.supertraits_of_trait_ref(trait_ref)));
+}
+
+fn test_higher_ranked_clauses(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "higher_ranked_clauses::Sub");
+    let tr = find_trait(tcx, "higher_ranked_clauses::Tr");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, sub).upcast(tcx);
+
+    let mut elaborator = elaborate(tcx, [seed]).with_higher_ranked_tracking();
+    assert_eq!(elaborator.by_ref().count(), 4);
+    // Only `for<'a> Self: Tr<'a>` binds more vars than the clause it is elaborated from.
+    // `for<'a> Self: Up<'a>` binds just as many as `for<'a> Self: Tr<'a>`.
+    let [clause] = elaborator.higher_ranked_clauses() else {
+        panic!("{:?}", elaborator.higher_ranked_clauses());
+    };
+    assert_eq!(clause.as_trait_clause().unwrap().def_id(), tr);
+    assert_eq!(clause.kind().bound_vars().len(), 1);
+    assert!(elaborate(tcx, [seed]).higher_ranked_clauses().is_empty());
+}
+
+fn test_supertraits_for_self(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "supertraits_for_self::Sub");
+    let base = find_trait(tcx, "supertraits_for_self::Base");
+    let args = tcx.mk_args(&[tcx.types.u16.into()]);
+
+    let trait_refs: Vec<_> = supertraits_for_self(tcx, sub, tcx.types.u8, args).collect();
+    // `u8: Sub<u16>` and `u8: Base<u16>`.
+    let expected = [sub, base].map(|def_id| {
+        ty::Binder::dummy(ty::TraitRef::new(tcx, def_id, [tcx.types.u8, tcx.types.u16]))
+    });
+    assert_eq!(trait_refs, expected);
+}
+
+fn test_common_supertrait(tcx: TyCtxt<'_>) {
+    let [mid, left, right, other] = ["Mid", "Left", "Right", "Other"]
+        .map(|name| find_trait(tcx, &format!("common_supertrait::{name}")));
+    let trait_ref = |def_id, args: &[ty::GenericArg<'_>]| {
+        ty::Binder::dummy(ty::TraitRef::new(tcx, def_id, args.iter().copied()))
+    };
+    // `u8: Left<u16>` and `u32: Right`, whose generic args are not taken into account.
+    let left = trait_ref(left, &[tcx.types.u8.into(), tcx.types.u16.into()]);
+    let right = trait_ref(right, &[tcx.types.u32.into()]);
+    let other = trait_ref(other, &[tcx.types.u8.into()]);
+
+    assert_eq!(common_supertrait(tcx, left, right), Some(mid));
+    assert_eq!(common_supertrait(tcx, right, left), Some(mid));
+    assert_eq!(common_supertrait(tcx, left, other), None);
+}
+
+fn test_strict_supertraits(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "strict_supertraits::Sub");
+    let trait_ref = ty::Binder::dummy(ty::TraitRef::identity(tcx, sub));
+
+    let including_self: Vec<_> = supertraits_including_self(tcx, trait_ref).collect();
+    // `Self: Sub`, `Self: Left`, `Self: Right` and `Self: Base`.
+    assert_eq!(including_self.len(), 4);
+    assert_eq!(including_self[0], trait_ref);
+    assert_eq!(including_self, supertraits(tcx, trait_ref).collect::<Vec<_>>());
+    // The same, but without `Self: Sub`.
+    assert_eq!(strict_supertraits(tcx, trait_ref).collect::<Vec<_>>(), including_self[1..]);
+}
+
+fn test_identity_supertraits(tcx: TyCtxt<'_>) {
+    let sub = find_trait(tcx, "identity_supertraits::Sub");
+    let trait_ref = ty::Binder::dummy(ty::TraitRef::identity(tcx, sub));
+
+    let cached = tcx.identity_supertraits(sub);
+    // `Self: Sub<T>` and `Self: Base<T>`, with the generic params of `Sub` as args.
+    assert_eq!(cached.len(), 2);
+    assert_eq!(cached[0], trait_ref);
+    assert_eq!(cached, supertraits(tcx, trait_ref).collect::<Vec<_>>());
+    // The same slice as the one of `supertraits_of_trait_ref`.
+    assert!(std::ptr::eq(cached, tcx.supertraits_of_trait_ref(trait_ref)));
+}
+
+fn main() {
+    common::run_tests(
+        "supertraits",
+        INPUT,
+        &[
+            test_transitive_bounds_unique_traits,
+            test_supertrait_must_implement_one_of,
+            test_elaborated_clauses_interned,
+            test_nearest_common_supertrait,
+            test_elaborate_trait_ref,
+            test_reaches_trait,
+            test_supertraits_cached,
+            test_supertrait_path,
+            test_supertrait_def_ids,
+            test_supertraits_of_trait_ref,
+            test_higher_ranked_clauses,
+            test_supertraits_for_self,
+            test_common_supertrait,
+            test_strict_supertraits,
+            test_identity_supertraits,
+        ],
+    );
+}
+
+const INPUT: &str = r#"
+#![feature(rustc_attrs)]
+
+pub mod transitive_bounds_unique_traits {
+    pub trait Foo<T> {}
+    pub trait Sub: Foo<u32> + Foo<i32> + 'static {}
+}
+
+pub mod supertrait_must_implement_one_of {
+    pub trait Super {}
+
+    #[rustc_must_implement_one_of(eq, neq)]
+    pub trait Equal: Super {
+        fn eq(&self, other: &Self) -> bool {
+            !self.neq(other)
+        }
+
+        fn neq(&self, other: &Self) -> bool {
+            !self.eq(other)
+        }
+    }
+
+    #[rustc_must_implement_one_of(on, off)]
+    pub trait Toggle {
+        fn on(&self) {}
+        fn off(&self) {}
+    }
+
+    pub trait Sub: Equal + Toggle {}
+}
+
+pub mod elaborated_clauses_interned {
+    pub trait Base {}
+    pub trait Sub: Base + 'static {}
+}
+
+pub mod nearest_common_supertrait {
+    pub trait Root {}
+    pub trait Mid: Root {}
+    pub trait Left: Mid {}
+    pub trait Right: Mid + Root {}
+    pub trait Other {}
+}
+
+pub mod elaborate_trait_ref {
+    pub trait Base {}
+    pub trait Sub: Base + 'static {}
+}
+
+pub mod reaches_trait {
+    pub trait Base {}
+    pub trait Other {}
+    pub trait Unrelated {}
+    pub trait Sub: Base
+    where
+        Self::Assoc: Other,
+    {
+        type Assoc;
+    }
+}
+
+pub mod supertraits_cached {
+    pub trait Base {}
+    pub trait Left: Base {}
+    pub trait Right: Base {}
+    pub trait Sub: Left + Right {}
+}
+
+pub mod supertrait_path {
+    pub trait Unrelated {}
+    pub trait Other {}
+    pub trait C {}
+    pub trait B: Other + C {}
+    pub trait A: B
+    where
+        Self::Assoc: Unrelated,
+    {
+        type Assoc;
+    }
+}
+
+pub mod supertraits_of_trait_ref {
+    pub trait Base<T> {}
+    pub trait Sub<T>: Base<T> + Base<Self> {}
+}
+
+pub mod higher_ranked_clauses {
+    pub trait Up<'a> {}
+    pub trait Tr<'a>: Up<'a> {}
+    pub trait Plain {}
+    pub trait Sub: for<'a> Tr<'a> + Plain {}
+}
+
+pub mod supertraits_for_self {
+    pub trait Base<T> {}
+    pub trait Sub<T>: Base<T> {}
+}
+
+pub mod common_supertrait {
+    pub trait Mid {}
+    pub trait Left<T>: Mid {}
+    pub trait Right: Mid {}
+    pub trait Other {}
+}
+
+pub mod strict_supertraits {
+    pub trait Base {}
+    pub trait Left: Base {}
+    pub trait Right: Base {}
+    pub trait Sub: Left + Right {}
+}
+
+pub mod identity_supertraits {
+    pub trait Base<T> {}
+    pub trait Sub<T>: Base<T> {}
+}
+"#;
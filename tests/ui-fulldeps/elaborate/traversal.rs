@@ -0,0 +1,485 @@
+//@ run-pass
+//! Tests of the order in which the elaborator yields predicates, of the graphs of the
+//! predicates it elaborates, and of what it yields once it is exhausted or restarted.
+
+//@ ignore-stage1
+//@ ignore-cross-compile
+//@ ignore-remote
+//@ ignore-windows-gnu mingw has troubles with linking https://github.com/rust-lang/rust/pull/116837
+//@ edition: 2021
+
+#![feature(rustc_private)]
+#![feature(control_flow_enum)]
+
+#[macro_use]
+extern crate rustc_smir;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_infer;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate stable_mir;
+
+#[path = "auxiliary/common.rs"]
+mod common;
+
+use common::find_trait;
+use rustc_hir::def_id::DefId;
+use rustc_infer::traits::util::{
+    elaborate, elaborate_batch_fair, elaborate_find, elaborate_par, elaborate_with_metadata,
+    implication_graph, ElaborateSortedExt, ElaboratedPredicate, PendingObligation, SpanCollecting,
+    WorkQueue,
+};
+use rustc_middle::ty::{self, TyCtxt, Upcast};
+use std::cell::RefCell;
+use std::iter::FusedIterator;
+use std::rc::Rc;
+
+fn test_next_level(tcx: TyCtxt<'_>) {
+    let [a, b, c, d] =
+        ["A", "B", "C", "D"].map(|name| find_trait(tcx, &format!("next_level::{name}")));
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, a).upcast(tcx);
+    let mut elaborator = elaborate(tcx, [seed]);
+    let mut next_level = || -> Vec<DefId> {
+        elaborator
+            .next_level()
+            .into_iter()
+            .map(|clause| clause.as_trait_clause().unwrap().def_id())
+            .collect()
+    };
+    assert_eq!(next_level(), [a]);
+    assert_eq!(next_level(), [b, c]);
+    assert_eq!(next_level(), [d]);
+    assert!(next_level().is_empty());
+}
+
+/// The forest built by `build_adjacency` when elaborating `Self: A`, after first yielding
+/// `skip` obligations, as the name of each trait with the sorted names of its children,
+/// sorted by name.
+fn adjacency(tcx: TyCtxt<'_>, skip: usize) -> Vec<(String, Vec<String>)> {
+    let a = find_trait(tcx, "build_adjacency::A");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, a).upcast(tcx);
+    let mut elaborator = elaborate(tcx, [seed]);
+    elaborator.by_ref().take(skip).for_each(drop);
+    let (nodes, children) = elaborator.build_adjacency();
+    let name = |index: usize| tcx.item_name(nodes[index].as_trait_clause().unwrap().def_id());
+    let mut adjacency: Vec<_> = children
+        .iter()
+        .enumerate()
+        .map(|(index, children)| {
+            let mut children: Vec<_> =
+                children.iter().map(|&child| name(child).to_string()).collect();
+            children.sort();
+            (name(index).to_string(), children)
+        })
+        .collect();
+    adjacency.sort();
+    adjacency
+}
+
+fn test_build_adjacency(tcx: TyCtxt<'_>) {
+    let node = |name: &str, children: &[&str]| {
+        (name.to_string(), children.iter().map(|child| child.to_string()).collect::<Vec<_>>())
+    };
+    assert_eq!(
+        adjacency(tcx, 0),
+        [node("A", &["B", "C"]), node("B", &["D"]), node("C", &[]), node("D", &[])]
+    );
+    // `Self: A` was yielded before, so `Self: B` and `Self: C` are roots now, but the
+    // indices of the children of `Self: B` still refer to the returned obligations.
+    assert_eq!(adjacency(tcx, 1), [node("B", &["D"]), node("C", &[]), node("D", &[])]);
+}
+
+fn test_implication_graph(tcx: TyCtxt<'_>) {
+    let top = find_trait(tcx, "implication_graph::Top");
+    let seed: ty::Predicate<'_> = ty::TraitRef::identity(tcx, top).upcast(tcx);
+    let name = |pred: ty::Predicate<'_>| {
+        let data = pred.as_clause().and_then(|clause| clause.as_trait_clause()).unwrap();
+        tcx.item_name(data.def_id()).to_string()
+    };
+
+    let mut graph: Vec<_> = implication_graph(tcx, [seed])
+        .into_iter()
+        .map(|(pred, children)| {
+            let mut children: Vec<_> = children.into_iter().map(name).collect();
+            children.sort();
+            (name(pred), children)
+        })
+        .collect();
+    graph.sort();
+    assert_eq!(
+        graph,
+        [
+            ("Base".to_string(), vec![]),
+            ("Left".to_string(), vec!["Base".to_string()]),
+            ("Right".to_string(), vec!["Base".to_string()]),
+            ("Top".to_string(), vec!["Left".to_string(), "Right".to_string()]),
+        ]
+    );
+}
+
+/// Exhausts `iter`, checking that it keeps returning `None` afterwards, and returns how
+/// many items it yielded.
+fn exhaust<It: FusedIterator>(mut iter: It) -> usize {
+    let count = iter.by_ref().count();
+    for _ in 0..3 {
+        assert!(iter.next().is_none());
+    }
+    count
+}
+
+fn test_fused(tcx: TyCtxt<'_>) {
+    let a = find_trait(tcx, "fused::A");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, a).upcast(tcx);
+    // `Self: A` and `Self: B`.
+    assert_eq!(exhaust(elaborate(tcx, [seed])), 2);
+    assert_eq!(exhaust(elaborate(tcx, [seed]).filter_to_traits()), 2);
+}
+
+fn test_reset<'tcx>(tcx: TyCtxt<'tcx>) {
+    let [wide, narrow] = ["Wide", "Narrow"].map(|name| -> ty::Clause<'tcx> {
+        let def_id = find_trait(tcx, &format!("reset::{name}"));
+        ty::TraitRef::identity(tcx, def_id).upcast(tcx)
+    });
+    let reset = |seed| {
+        let mut elaborator = elaborate(tcx, [wide]);
+        elaborator.by_ref().for_each(drop);
+        elaborator.reset([seed])
+    };
+
+    let clauses: Vec<_> = reset(narrow).collect();
+    assert_eq!(clauses, elaborate(tcx, [narrow]).collect::<Vec<_>>());
+    // `Self: Narrow` and `Self: A`, in a set that still has room for the five predicates
+    // elaborated from `Self: Wide`.
+    let visited = reset(narrow).collect_predicate_set();
+    assert_eq!(visited.len(), 2);
+    assert!(visited.capacity() >= 5);
+}
+
+fn test_last(tcx: TyCtxt<'_>) {
+    let a = find_trait(tcx, "last::A");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, a).upcast(tcx);
+    let clauses: Vec<_> = elaborate(tcx, [seed]).collect();
+    // `Self: A`, `Self: B` and `Self: C`, as `Self: C` is only pushed once `Self: B`
+    // is yielded.
+    assert_eq!(clauses.len(), 3);
+    assert_eq!(elaborate(tcx, [seed]).last().as_ref(), clauses.last());
+}
+
+fn test_elaborate_find<'tcx>(tcx: TyCtxt<'tcx>) {
+    let [a, b] = ["A", "B"].map(|name| -> ty::Clause<'tcx> {
+        let def_id = find_trait(tcx, &format!("elaborate_find::{name}"));
+        ty::TraitRef::identity(tcx, def_id).upcast(tcx)
+    });
+    // Records the span of each supertrait bound that is elaborated.
+    let spans = Rc::new(RefCell::new(vec![]));
+    let seed = SpanCollecting::new(a, spans.clone());
+
+    let found = elaborate_find(tcx, [seed], b.as_predicate());
+    assert_eq!(found.map(|found| found.obligation), Some(b));
+    // The bounds of `A` and `B`, but not the one of `C`.
+    assert_eq!(spans.borrow().len(), 2);
+
+    assert!(elaborate_find(tcx, [b], a.as_predicate()).is_none());
+}
+
+fn test_with_provenance<'tcx>(tcx: TyCtxt<'tcx>) {
+    let [a, b, c, unrelated] = ["A", "B", "C", "Unrelated"]
+        .map(|name| find_trait(tcx, &format!("with_provenance::{name}")));
+    let pred = |def_id| -> ty::Predicate<'tcx> { ty::TraitRef::identity(tcx, def_id).upcast(tcx) };
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, a).upcast(tcx);
+
+    let mut elaborator = elaborate(tcx, [seed]).with_provenance();
+    elaborator.by_ref().for_each(drop);
+    assert_eq!(elaborator.provenance_of(pred(a)), Some(&[][..]));
+    assert_eq!(elaborator.provenance_of(pred(b)), Some(&[(a, 0)][..]));
+    // `C` is the second supertrait of `B`.
+    assert_eq!(elaborator.provenance_of(pred(c)), Some(&[(a, 0), (b, 1)][..]));
+    assert_eq!(elaborator.provenance_of(pred(unrelated)), None);
+}
+
+/// A queue that pops the shallowest obligation first, and among those the one that was
+/// pushed first, so that obligations are elaborated breadth-first.
+struct ByDepth<T>(Vec<PendingObligation<T>>);
+
+impl<T> WorkQueue<PendingObligation<T>> for ByDepth<T> {
+    fn push(&mut self, item: PendingObligation<T>) {
+        self.0.push(item);
+    }
+
+    fn pop(&mut self) -> Option<PendingObligation<T>> {
+        let index = (0..self.0.len()).min_by_key(|&index| self.0[index].depth)?;
+        Some(self.0.remove(index))
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn for_each_mut(&mut self, f: impl FnMut(&mut PendingObligation<T>)) {
+        self.0.iter_mut().for_each(f)
+    }
+}
+
+/// The names of the traits of `clauses`, which must all be trait clauses.
+fn trait_names<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    clauses: impl Iterator<Item = ty::Clause<'tcx>>,
+) -> Vec<String> {
+    clauses
+        .map(|clause| tcx.item_name(clause.as_trait_clause().unwrap().def_id()).to_string())
+        .collect()
+}
+
+fn test_with_queue(tcx: TyCtxt<'_>) {
+    let a = find_trait(tcx, "with_queue::A");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, a).upcast(tcx);
+    // The default stack elaborates depth-first, starting with the last supertrait.
+    assert_eq!(trait_names(tcx, elaborate(tcx, [seed])), ["A", "C", "D", "B"]);
+    let elaborator = elaborate(tcx, [seed]).with_queue(ByDepth(vec![]));
+    assert_eq!(trait_names(tcx, elaborator), ["A", "B", "C", "D"]);
+}
+
+fn test_multi_path_predicates(tcx: TyCtxt<'_>) {
+    let top = find_trait(tcx, "multi_path_predicates::Top");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, top).upcast(tcx);
+    let mut elaborator = elaborate(tcx, [seed]).with_multi_path_tracking();
+    let yielded = trait_names(tcx, elaborator.by_ref());
+    assert_eq!(yielded.len(), 4);
+
+    let multi_path: Vec<_> = elaborator
+        .multi_path_predicates()
+        .into_iter()
+        .map(|(pred, parents)| {
+            let name = tcx.item_name(pred.as_clause().unwrap().as_trait_clause().unwrap().def_id());
+            let mut parents: Vec<_> = parents.iter().map(|&i| yielded[i].as_str()).collect();
+            parents.sort();
+            (name.to_string(), parents)
+        })
+        .collect();
+    // `Base` is reached via both sides of the diamond, all other traits via one path.
+    assert_eq!(multi_path, [("Base".to_string(), vec!["Left", "Right"])]);
+}
+
+fn test_elaborate_batch_fair(tcx: TyCtxt<'_>) {
+    let seeds: [ty::Clause<'_>; 2] = ["A1", "B1"].map(|name| {
+        let tr = find_trait(tcx, &format!("elaborate_batch_fair::{name}"));
+        ty::TraitRef::identity(tcx, tr).upcast(tcx)
+    });
+    // The seeds take turns, so neither chain is exhausted when the fuel runs out.
+    let names = trait_names(tcx, elaborate_batch_fair(tcx, seeds.to_vec(), 4));
+    assert_eq!(names, ["A1", "B1", "A2", "B2"]);
+    // Without a tight budget, each chain is elaborated to its end.
+    let names = trait_names(tcx, elaborate_batch_fair(tcx, seeds.to_vec(), 100));
+    assert_eq!(names, ["A1", "B1", "A2", "B2", "A3", "B3", "A4"]);
+}
+
+fn test_elaborate_par(tcx: TyCtxt<'_>) {
+    let seeds: Vec<ty::Clause<'_>> = ["A", "C"]
+        .map(|name| {
+            let tr = find_trait(tcx, &format!("elaborate_par::{name}"));
+            ty::TraitRef::identity(tcx, tr).upcast(tcx)
+        })
+        .to_vec();
+
+    let closures = elaborate_par(tcx, seeds.clone());
+    let names: Vec<_> =
+        closures.iter().map(|closure| trait_names(tcx, closure.iter().copied())).collect();
+    // Each seed is elaborated separately, so the shared `B` is part of both closures.
+    assert_eq!(names, [vec!["A", "B"], vec!["C", "D", "B"]]);
+    for (seed, closure) in seeds.into_iter().zip(closures) {
+        assert_eq!(closure, elaborate(tcx, [seed]).collect::<Vec<_>>());
+    }
+}
+
+fn test_try_for_each(tcx: TyCtxt<'_>) {
+    let a = find_trait(tcx, "try_for_each::A");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, a).upcast(tcx);
+
+    let mut seen = vec![];
+    let result = elaborate(tcx, [seed]).try_for_each(|clause| {
+        seen.push(clause);
+        if seen.len() == 2 { Err(clause) } else { Ok(()) }
+    });
+    // `Self: A` and `Self: B`, but `f` is not applied to `Self: C` after the error.
+    assert_eq!(trait_names(tcx, seen.iter().copied()), ["A", "B"]);
+    assert_eq!(result, Err(seen[1]));
+
+    let mut count = 0;
+    let result = elaborate(tcx, [seed]).try_for_each(|_| {
+        count += 1;
+        Ok::<(), ()>(())
+    });
+    assert_eq!(result, Ok(()));
+    assert_eq!(count, 3);
+}
+
+fn test_elaborate_with_metadata(tcx: TyCtxt<'_>) {
+    let a = find_trait(tcx, "elaborate_with_metadata::A");
+    let seed: ty::Clause<'_> = ty::TraitRef::identity(tcx, a).upcast(tcx);
+
+    let metadata: Vec<_> = elaborate_with_metadata(tcx, [seed])
+        .map(|ElaboratedPredicate { item, depth, parent }| {
+            (tcx.item_name(item.as_trait_clause().unwrap().def_id()).to_string(), depth, parent)
+        })
+        .collect();
+    // Parents are identified by the index of the obligation in the order they are yielded.
+    assert_eq!(
+        metadata,
+        [
+            ("A".to_string(), 0, None),
+            ("C".to_string(), 1, Some(0)),
+            ("D".to_string(), 2, Some(1)),
+            ("B".to_string(), 1, Some(0)),
+        ]
+    );
+}
+
+fn test_into_sorted_vec(tcx: TyCtxt<'_>) {
+    let [a, b] = ["A", "B"].map(|name| {
+        let def_id = find_trait(tcx, &format!("into_sorted_vec::{name}"));
+        ty::TraitRef::identity(tcx, def_id).upcast(tcx)
+    });
+    let forward: Vec<ty::Clause<'_>> = elaborate(tcx, [a, b]).collect();
+    let backward: Vec<ty::Clause<'_>> = elaborate(tcx, [b, a]).collect();
+    assert_ne!(forward, backward);
+
+    // Sorted, the closure is the same whichever order the seeds are in.
+    let sorted = elaborate(tcx, [a, b]).into_sorted_vec();
+    assert_eq!(sorted.len(), 4);
+    assert_eq!(sorted, elaborate(tcx, [b, a]).into_sorted_vec());
+    assert!(sorted.is_sorted_by_key(|clause| tcx.anonymized_predicate_hash(clause.as_predicate())));
+}
+
+fn main() {
+    common::run_tests(
+        "traversal",
+        INPUT,
+        &[
+            test_next_level,
+            test_build_adjacency,
+            test_implication_graph,
+            test_fused,
+            test_reset,
+            test_last,
+            test_elaborate_find,
+            test_with_provenance,
+            test_with_queue,
+            test_multi_path_predicates,
+            test_elaborate_batch_fair,
+            test_elaborate_par,
+            test_try_for_each,
+            test_elaborate_with_metadata,
+            test_into_sorted_vec,
+        ],
+    );
+}
+
+const INPUT: &str = r#"
+pub mod next_level {
+    pub trait D {}
+    pub trait C {}
+    pub trait B: D {}
+    pub trait A: B + C {}
+}
+
+pub mod build_adjacency {
+    pub trait D {}
+    pub trait C {}
+    pub trait B: D {}
+    pub trait A: B + C {}
+}
+
+pub mod implication_graph {
+    pub trait Base {}
+    pub trait Left: Base {}
+    pub trait Right: Base {}
+    pub trait Top: Left + Right {}
+}
+
+pub mod fused {
+    pub trait B {}
+    pub trait A: B {}
+}
+
+pub mod reset {
+    pub trait A {}
+    pub trait B {}
+    pub trait C {}
+    pub trait D {}
+    pub trait Wide: A + B + C + D {}
+    pub trait Narrow: A {}
+}
+
+pub mod last {
+    pub trait C {}
+    pub trait B: C {}
+    pub trait A: B {}
+}
+
+pub mod elaborate_find {
+    pub trait D {}
+    pub trait C: D {}
+    pub trait B: C {}
+    pub trait A: B {}
+}
+
+pub mod with_provenance {
+    pub trait Unrelated {}
+    pub trait Other {}
+    pub trait C {}
+    pub trait B: Other + C {}
+    pub trait A: B {}
+}
+
+pub mod with_queue {
+    pub trait D {}
+    pub trait C: D {}
+    pub trait B {}
+    pub trait A: B + C {}
+}
+
+pub mod multi_path_predicates {
+    pub trait Base {}
+    pub trait Left: Base {}
+    pub trait Right: Base {}
+    pub trait Top: Left + Right {}
+}
+
+pub mod elaborate_batch_fair {
+    pub trait A4 {}
+    pub trait A3: A4 {}
+    pub trait A2: A3 {}
+    pub trait A1: A2 {}
+    pub trait B3 {}
+    pub trait B2: B3 {}
+    pub trait B1: B2 {}
+}
+
+pub mod elaborate_par {
+    pub trait B {}
+    pub trait A: B {}
+    pub trait D {}
+    pub trait C: B + D {}
+}
+
+pub mod try_for_each {
+    pub trait C {}
+    pub trait B: C {}
+    pub trait A: B {}
+}
+
+pub mod elaborate_with_metadata {
+    pub trait D {}
+    pub trait C: D {}
+    pub trait B {}
+    pub trait A: B + C {}
+}
+
+pub mod into_sorted_vec {
+    pub trait BaseA {}
+    pub trait BaseB {}
+    pub trait A: BaseA {}
+    pub trait B: BaseB {}
+}
+"#;
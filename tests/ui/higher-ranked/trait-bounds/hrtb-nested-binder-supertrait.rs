@@ -0,0 +1,39 @@
+//@ check-pass
+// Test that elaborating `for<'a> T: Trait<'a>` where `Trait<'a>: for<'b> Super<'a, 'b>`
+// composes the two binders into `for<'a, 'b> T: Super<'a, 'b>`, with neither of the
+// bound regions being captured by the wrong binder.
+
+trait Super<'a, 'b> {
+    fn both(&self, x: &'a u8, y: &'b u8) -> (&'a u8, &'b u8);
+}
+
+trait Trait<'a>: for<'b> Super<'a, 'b> {}
+
+fn want_super<T>(_: &T)
+where
+    T: for<'a, 'b> Super<'a, 'b>,
+{
+}
+
+fn want_super_swapped<T>(_: &T)
+where
+    T: for<'b, 'a> Super<'a, 'b>,
+{
+}
+
+fn elaborated<T>(t: &T)
+where
+    T: for<'a> Trait<'a>,
+{
+    want_super(t);
+    want_super_swapped(t);
+}
+
+fn call<'x, 'y, T>(t: &T, x: &'x u8, y: &'y u8) -> (&'x u8, &'y u8)
+where
+    T: for<'a> Trait<'a>,
+{
+    t.both(x, y)
+}
+
+fn main() {}
@@ -0,0 +1,35 @@
+#![recursion_limit = "10"]
+#![allow(dead_code)]
+
+// Test that the where-clauses of a function are only elaborated through supertrait
+// hierarchies up to the recursion limit, so that very deep hierarchies cannot make
+// elaboration run away.
+
+trait T0: T1 {}
+trait T1: T2 {}
+trait T2: T3 {}
+trait T3: T4 {}
+trait T4: T5 {}
+trait T5: T6 {}
+trait T6: T7 {}
+trait T7: T8 {}
+trait T8: T9 {}
+trait T9: T10 {}
+trait T10: T11 {}
+trait T11: T12 {}
+trait T12: T13 {}
+trait T13: T14 {}
+trait T14: T15 {}
+trait T15: T16 {}
+trait T16 {}
+
+fn needs_shallow<T: T5>() {}
+fn needs_deepest<T: T16>() {}
+
+fn uses_deepest<T: T0>() {
+    needs_shallow::<T>();
+    needs_deepest::<T>();
+    //~^ ERROR the trait bound `T: T16` is not satisfied
+}
+
+fn main() {}